@@ -0,0 +1,119 @@
+//! Time-ordered key generators.
+//!
+//! Both formats sort lexicographically in creation order and are monotonic within a process: ids
+//! generated in the same tick bump the random part instead of re-rolling it. The random part is
+//! seeded from std's hasher keys and is not suitable for secrets.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const BASE62: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const ULID_RANDOM_MASK: u128 = (1 << 80) - 1;
+/// KSUID timestamps count seconds from 2014-05-13T16:53:20Z.
+const KSUID_EPOCH: u64 = 1_400_000_000;
+
+static LAST_ULID: Mutex<(u64, u128)> = Mutex::new((0, 0));
+static LAST_KSUID: Mutex<(u32, u128)> = Mutex::new((0, 0));
+
+/// 26-character Crockford base32 ULID: 48-bit millisecond timestamp followed by 80 random bits.
+pub fn ulid() -> String {
+    let now = unix_time().as_millis() as u64;
+    let (millis, random) = {
+        let mut last = LAST_ULID.lock().unwrap_or_else(|e| e.into_inner());
+        let next = if now > last.0 {
+            (now, random_u128() & ULID_RANDOM_MASK)
+        } else if last.1 < ULID_RANDOM_MASK {
+            (last.0, last.1 + 1)
+        } else {
+            (last.0 + 1, 0)
+        };
+        *last = next;
+        next
+    };
+
+    let value = ((millis as u128) << 80) | random;
+    (0..26)
+        .map(|i| CROCKFORD[((value >> (125 - 5 * i)) & 0x1f) as usize] as char)
+        .collect()
+}
+
+/// 27-character base62 KSUID: 32-bit second timestamp followed by a 128-bit payload.
+pub fn ksuid() -> String {
+    let now = unix_time().as_secs().saturating_sub(KSUID_EPOCH) as u32;
+    let (seconds, payload) = {
+        let mut last = LAST_KSUID.lock().unwrap_or_else(|e| e.into_inner());
+        let next = if now > last.0 {
+            (now, random_u128())
+        } else if last.1 < u128::MAX {
+            (last.0, last.1 + 1)
+        } else {
+            (last.0 + 1, 0)
+        };
+        *last = next;
+        next
+    };
+
+    let mut bytes = [0u8; 20];
+    bytes[..4].copy_from_slice(&seconds.to_be_bytes());
+    bytes[4..].copy_from_slice(&payload.to_be_bytes());
+    encode_base62(bytes)
+}
+
+fn encode_base62(mut bytes: [u8; 20]) -> String {
+    let mut out = [b'0'; 27];
+    for slot in out.iter_mut().rev() {
+        let mut remainder = 0u32;
+        for byte in bytes.iter_mut() {
+            let acc = (remainder << 8) | *byte as u32;
+            *byte = (acc / 62) as u8;
+            remainder = acc % 62;
+        }
+        *slot = BASE62[remainder as usize];
+    }
+    out.iter().map(|b| *b as char).collect()
+}
+
+fn unix_time() -> std::time::Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
+fn random_u128() -> u128 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut halves = [0u64; 2];
+    for half in halves.iter_mut() {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(unix_time().as_nanos());
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        *half = hasher.finish();
+    }
+    ((halves[0] as u128) << 64) | halves[1] as u128
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ulids_sort_in_creation_order() {
+        let ids: Vec<String> = (0..1000).map(|_| ulid()).collect();
+        assert!(ids.iter().all(|id| id.len() == 26));
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn ksuids_sort_in_creation_order() {
+        let ids: Vec<String> = (0..1000).map(|_| ksuid()).collect();
+        assert!(ids.iter().all(|id| id.len() == 27));
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn base62_pads_to_fixed_width() {
+        assert_eq!(encode_base62([0; 20]), "0".repeat(27));
+        assert_eq!(encode_base62([0xff; 20]), "aWgEPTl1tmebfsQzFP4bxwgy80V");
+    }
+}
@@ -0,0 +1,319 @@
+//! Helpers for trees that store `serde_json::Value` documents.
+
+use std::cmp::Ordering;
+
+use serde_json::Value;
+
+use crate::{jsonpath, AcornError, AcornResult};
+
+/// Recursively merge `patch` into `target`: objects are merged key by key, while scalars and arrays
+/// in `patch` replace what was there. A non-object `target` is replaced wholesale.
+pub fn deep_merge(target: &mut Value, patch: &Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                match target.get_mut(key) {
+                    Some(existing) => deep_merge(existing, value),
+                    None => {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (target, patch) => *target = patch.clone(),
+    }
+}
+
+/// Apply an RFC 7386 merge patch: like [`deep_merge`], except that `null` members delete the key.
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let Value::Object(target) = target else {
+        unreachable!("target was just made an object")
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// One RFC 6902 JSON Patch operation. Paths are RFC 6901 JSON pointers.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOp {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+/// Apply `ops` in order. Either every operation succeeds or `doc` is left untouched.
+pub fn apply_patch(doc: &mut Value, ops: &[PatchOp]) -> AcornResult<()> {
+    let mut working = doc.clone();
+    for op in ops {
+        apply_op(&mut working, op)?;
+    }
+    *doc = working;
+    Ok(())
+}
+
+fn apply_op(doc: &mut Value, op: &PatchOp) -> AcornResult<()> {
+    let (path, from) = match op {
+        PatchOp::Add { path, .. }
+        | PatchOp::Remove { path }
+        | PatchOp::Replace { path, .. }
+        | PatchOp::Test { path, .. } => (path, None),
+        PatchOp::Move { from, path } | PatchOp::Copy { from, path } => (path, Some(from)),
+    };
+    for pointer in std::iter::once(path).chain(from) {
+        validate_pointer(pointer)?;
+    }
+
+    match op {
+        PatchOp::Add { path, value } => add(doc, path, value.clone()),
+        PatchOp::Remove { path } => remove(doc, path).map(drop),
+        PatchOp::Replace { path, value } => {
+            *doc.pointer_mut(path).ok_or_else(|| missing(path))? = value.clone();
+            Ok(())
+        }
+        PatchOp::Move { from, path } => {
+            if path.starts_with(&format!("{from}/")) {
+                return Err(patch_error(format!(
+                    "cannot move {from} into its own child {path}"
+                )));
+            }
+            let value = remove(doc, from)?;
+            add(doc, path, value)
+        }
+        PatchOp::Copy { from, path } => {
+            let value = doc.pointer(from).cloned().ok_or_else(|| missing(from))?;
+            add(doc, path, value)
+        }
+        PatchOp::Test { path, value } => match doc.pointer(path) {
+            Some(actual) if actual == value => Ok(()),
+            _ => Err(patch_error(format!("test failed at {path}"))),
+        },
+    }
+}
+
+fn add(doc: &mut Value, path: &str, value: Value) -> AcornResult<()> {
+    let Some((parent, token)) = split_pointer(path) else {
+        *doc = value;
+        return Ok(());
+    };
+    match doc.pointer_mut(parent).ok_or_else(|| missing(parent))? {
+        Value::Object(map) => {
+            map.insert(token, value);
+        }
+        Value::Array(items) if token == "-" => items.push(value),
+        Value::Array(items) => {
+            let index = array_index(&token, items.len() + 1, path)?;
+            items.insert(index, value);
+        }
+        _ => return Err(patch_error(format!("cannot add below a scalar at {path}"))),
+    }
+    Ok(())
+}
+
+fn remove(doc: &mut Value, path: &str) -> AcornResult<Value> {
+    let (parent, token) =
+        split_pointer(path).ok_or_else(|| patch_error("cannot remove the document root".into()))?;
+    match doc.pointer_mut(parent).ok_or_else(|| missing(parent))? {
+        Value::Object(map) => map.remove(&token).ok_or_else(|| missing(path)),
+        Value::Array(items) => {
+            let index = array_index(&token, items.len(), path)?;
+            Ok(items.remove(index))
+        }
+        _ => Err(missing(path)),
+    }
+}
+
+/// RFC 6901 pointers are either empty (the whole document) or start with `/`.
+fn validate_pointer(path: &str) -> AcornResult<()> {
+    if path.is_empty() || path.starts_with('/') {
+        Ok(())
+    } else {
+        Err(patch_error(format!("invalid JSON pointer {path:?}")))
+    }
+}
+
+/// Split a validated JSON pointer into its parent pointer and unescaped last token; `None` for the root.
+fn split_pointer(path: &str) -> Option<(&str, String)> {
+    let (parent, token) = path.rsplit_once('/')?;
+    Some((parent, token.replace("~1", "/").replace("~0", "~")))
+}
+
+/// Array tokens are `0` or digits without a leading zero; signs and padding are rejected.
+fn array_index(token: &str, len: usize, path: &str) -> AcornResult<usize> {
+    let canonical = !token.is_empty()
+        && token.bytes().all(|b| b.is_ascii_digit())
+        && (token == "0" || !token.starts_with('0'));
+    Some(token)
+        .filter(|_| canonical)
+        .and_then(|token| token.parse::<usize>().ok())
+        .filter(|index| *index < len)
+        .ok_or_else(|| patch_error(format!("array index out of range at {path}")))
+}
+
+fn missing(path: &str) -> AcornError {
+    patch_error(format!("no value at {path}"))
+}
+
+fn patch_error(message: String) -> AcornError {
+    AcornError::Patch(message)
+}
+
+/// Order JSON values by their natural type: numbers numerically, strings lexically, `false` before
+/// `true`. Values of different types order as bool < number < string < array < object, with nulls
+/// last; arrays and objects compare equal among themselves.
+pub fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => {
+            let (a, b) = (a.as_f64().unwrap_or(f64::NAN), b.as_f64().unwrap_or(f64::NAN));
+            a.total_cmp(&b)
+        }
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        _ => type_rank(a).cmp(&type_rank(b)),
+    }
+}
+
+/// Comparator ordering documents by the field at `path` (see [`jsonpath::get`]) using
+/// [`compare_values`]; documents missing the field sort with the nulls, last.
+pub fn compare_at(path: &str) -> impl Fn(&Value, &Value) -> Ordering + '_ {
+    move |a, b| compare_values(field(a, path), field(b, path))
+}
+
+fn field<'v>(doc: &'v Value, path: &str) -> &'v Value {
+    static NULL: Value = Value::Null;
+    jsonpath::get(doc, path).unwrap_or(&NULL)
+}
+
+fn type_rank(value: &Value) -> u8 {
+    match value {
+        Value::Bool(_) => 0,
+        Value::Number(_) => 1,
+        Value::String(_) => 2,
+        Value::Array(_) => 3,
+        Value::Object(_) => 4,
+        Value::Null => 5,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn deep_merge_recurses_into_objects_and_replaces_the_rest() {
+        let mut doc = json!({"name": "A", "tags": ["x"], "address": {"city": "Oslo", "zip": "0150"}});
+        deep_merge(&mut doc, &json!({"tags": ["y"], "address": {"city": "Bergen"}}));
+        assert_eq!(
+            doc,
+            json!({"name": "A", "tags": ["y"], "address": {"city": "Bergen", "zip": "0150"}})
+        );
+
+        let mut scalar = json!(5);
+        deep_merge(&mut scalar, &json!({"a": 1}));
+        assert_eq!(scalar, json!({"a": 1}));
+    }
+
+    #[test]
+    fn compares_numbers_numerically_with_nulls_last() {
+        let mut people = vec![
+            json!({"age": 2}),
+            json!({"name": "x"}),
+            json!({"age": 10}),
+            json!({"age": 1}),
+        ];
+        people.sort_by(compare_at("age"));
+        let ages: Vec<&Value> = people.iter().map(|p| &p["age"]).collect();
+        assert_eq!(ages, [&json!(1), &json!(2), &json!(10), &Value::Null]);
+
+        assert_eq!(compare_values(&json!("10"), &json!("2")), Ordering::Less);
+        assert_eq!(compare_values(&json!(true), &json!(0)), Ordering::Less);
+    }
+
+    #[test]
+    fn merge_patch_null_removes_member() {
+        let mut doc = json!({"name": "A", "email": "a@example.com", "meta": {"x": 1, "y": 2}});
+        merge_patch(&mut doc, &json!({"email": null, "meta": {"y": null, "z": 3}}));
+        assert_eq!(doc, json!({"name": "A", "meta": {"x": 1, "z": 3}}));
+    }
+
+    #[test]
+    fn json_patch_applies_every_operation() {
+        let mut doc = json!({"a": 1, "list": [1, 3], "old": "x"});
+        let ops: Vec<PatchOp> = serde_json::from_value(json!([
+            {"op": "add", "path": "/list/1", "value": 2},
+            {"op": "add", "path": "/list/-", "value": 4},
+            {"op": "replace", "path": "/a", "value": 10},
+            {"op": "move", "from": "/old", "path": "/new"},
+            {"op": "copy", "from": "/a", "path": "/b"},
+            {"op": "remove", "path": "/list/0"},
+            {"op": "test", "path": "/b", "value": 10}
+        ]))
+        .unwrap();
+
+        apply_patch(&mut doc, &ops).unwrap();
+        assert_eq!(doc, json!({"a": 10, "b": 10, "list": [2, 3, 4], "new": "x"}));
+    }
+
+    #[test]
+    fn rejects_malformed_pointers_and_array_indices() {
+        let original = json!({"foo": 1, "list": [1, 2, 3]});
+        let bad = [
+            PatchOp::Add {
+                path: "foo".into(),
+                value: json!(2),
+            },
+            PatchOp::Remove { path: "foo".into() },
+            PatchOp::Copy {
+                from: "foo".into(),
+                path: "/bar".into(),
+            },
+            PatchOp::Remove {
+                path: "/list/01".into(),
+            },
+            PatchOp::Add {
+                path: "/list/+1".into(),
+                value: json!(0),
+            },
+        ];
+        for op in bad {
+            let mut doc = original.clone();
+            assert!(matches!(apply_patch(&mut doc, &[op]), Err(AcornError::Patch(_))));
+            assert_eq!(doc, original);
+        }
+    }
+
+    #[test]
+    fn failing_test_op_leaves_document_unchanged() {
+        let original = json!({"a": 1});
+        let mut doc = original.clone();
+        let ops = [
+            PatchOp::Replace {
+                path: "/a".into(),
+                value: json!(2),
+            },
+            PatchOp::Test {
+                path: "/a".into(),
+                value: json!(3),
+            },
+        ];
+
+        assert!(matches!(apply_patch(&mut doc, &ops), Err(AcornError::Patch(_))));
+        assert_eq!(doc, original);
+    }
+}
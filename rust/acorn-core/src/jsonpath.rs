@@ -0,0 +1,34 @@
+//! Dotted-path field access into `serde_json::Value` documents.
+
+use serde_json::Value;
+
+/// Look up `path` in `value`. Segments are separated by `.`; a numeric segment indexes into an
+/// array (`items.0.sku`), any other segment names an object member. An empty path returns `value`.
+pub fn get<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    if path.is_empty() {
+        return Some(value);
+    }
+    path.split('.').try_fold(value, |current, segment| match current {
+        Value::Object(map) => map.get(segment),
+        Value::Array(items) => segment.parse::<usize>().ok().and_then(|index| items.get(index)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reads_nested_fields_and_array_elements() {
+        let doc = json!({"address": {"city": "Oslo"}, "items": [{"sku": "a-1"}, {"sku": "b-2"}]});
+
+        assert_eq!(get(&doc, "address.city"), Some(&json!("Oslo")));
+        assert_eq!(get(&doc, "items.1.sku"), Some(&json!("b-2")));
+        assert_eq!(get(&doc, ""), Some(&doc));
+        assert_eq!(get(&doc, "items.2.sku"), None);
+        assert_eq!(get(&doc, "address.city.name"), None);
+        assert_eq!(get(&doc, "missing"), None);
+    }
+}
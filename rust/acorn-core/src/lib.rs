@@ -1,12 +1,24 @@
 #![allow(dead_code)]
 
+use std::backtrace::{Backtrace, BacktraceStatus};
 use std::collections::{HashMap, VecDeque};
 use std::fmt::{self, Debug};
+use std::io::{BufRead, BufReader, Read, Write};
 use std::marker::PhantomData;
-use std::time::SystemTime;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
 
 use serde::{de::DeserializeOwned, Serialize};
 
+pub mod id;
+pub mod json;
+pub mod jsonpath;
+#[cfg(feature = "schema")]
+pub mod schema;
+
 pub type AcornResult<T> = Result<T, AcornError>;
 
 #[derive(Debug, thiserror::Error)]
@@ -20,10 +32,87 @@ pub enum AcornError {
         expected: Option<u64>,
         actual: Option<u64>,
     },
+    #[error("counter at {key} would overflow")]
+    Overflow { key: String },
     #[error("trunk operation failed: {0}")]
     Trunk(String),
     #[error("serialization failed: {0}")]
     Serialization(String),
+    #[error("patch failed: {0}")]
+    Patch(String),
+    #[error("schema violation at '{path}': {message}")]
+    SchemaViolation { path: String, message: String },
+    #[error("invalid configuration for {parameter}: {message}")]
+    Configuration {
+        parameter: &'static str,
+        message: String,
+    },
+    #[error("{operation} timed out after {duration_ms}ms")]
+    Timeout {
+        operation: &'static str,
+        duration_ms: u64,
+    },
+    #[error("i/o error during {operation}: {source}")]
+    Io {
+        operation: &'static str,
+        #[source]
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+}
+
+impl From<serde_json::Error> for AcornError {
+    fn from(e: serde_json::Error) -> Self {
+        AcornError::Serialization(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for AcornError {
+    fn from(source: std::io::Error) -> Self {
+        AcornError::io("io", source)
+    }
+}
+
+impl AcornError {
+    /// Wrap an I/O failure, capturing a backtrace when `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` asks for one.
+    pub fn io(operation: &'static str, source: std::io::Error) -> Self {
+        AcornError::Io {
+            operation,
+            source,
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    /// Backtrace captured when an I/O failure was wrapped, if capture was enabled. Only I/O errors
+    /// carry one: the other variants are expected outcomes, such as the version conflicts that
+    /// retry loops produce routinely, and capturing a stack for each would be wasted work.
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        match self {
+            AcornError::Io { backtrace, .. } if backtrace.status() == BacktraceStatus::Captured => {
+                Some(backtrace)
+            }
+            _ => None,
+        }
+    }
+
+    /// Name of the operation that failed, when the error carries one.
+    pub fn operation(&self) -> Option<&'static str> {
+        match self {
+            AcornError::Io { operation, .. } | AcornError::Timeout { operation, .. } => Some(*operation),
+            _ => None,
+        }
+    }
+}
+
+/// Attach an operation name to I/O failures while keeping the `io::Error` as the error source.
+pub trait IoResultExt<T> {
+    fn with_operation(self, operation: &'static str) -> AcornResult<T>;
+}
+
+impl<T> IoResultExt<T> for std::io::Result<T> {
+    fn with_operation(self, operation: &'static str) -> AcornResult<T> {
+        self.map_err(|source| AcornError::io(operation, source))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
@@ -68,6 +157,19 @@ pub struct Ttl {
     pub expires_at: SystemTime,
 }
 
+impl Ttl {
+    /// TTL expiring `duration` from now.
+    pub fn from_now(duration: Duration) -> Self {
+        Ttl {
+            expires_at: SystemTime::now() + duration,
+        }
+    }
+
+    pub fn at(expires_at: SystemTime) -> Self {
+        Ttl { expires_at }
+    }
+}
+
 /// Core storage abstraction shared by trunks.
 pub trait Trunk<T>: Send + Sync + Debug {
     fn get(&self, branch: &BranchId, key: &str) -> AcornResult<Option<Nut<T>>>;
@@ -77,6 +179,13 @@ pub trait Trunk<T>: Send + Sync + Debug {
         None
     }
     /// Compare-and-set style put that only writes when the expected version matches the current.
+    ///
+    /// This is the guard the read-modify-write helpers on [`Tree`] and [`EncodedTree`] build on. It
+    /// excludes concurrent writers only when the check and the write happen under one lock, which the
+    /// in-memory trunk does and the file trunk (check, then write) does not. A version identifies a
+    /// value only on trunks advertising [`TrunkCapability::MonotonicVersions`]; elsewhere a key that is
+    /// deleted and written again can match a version read before the delete. Trunks that report no
+    /// versions give those helpers nothing to guard an existing key with.
     fn put_if_version(
         &self,
         _branch: &BranchId,
@@ -86,10 +195,26 @@ pub trait Trunk<T>: Send + Sync + Debug {
     ) -> AcornResult<()> {
         Err(AcornError::NotImplemented)
     }
+    /// Put that only writes when the key is absent; conflicts report the existing version.
+    /// The default checks then writes and is not atomic; trunks holding a lock should override it.
+    fn put_if_absent(&self, branch: &BranchId, key: &str, nut: Nut<T>) -> AcornResult<()> {
+        if self.get(branch, key)?.is_some() {
+            return Err(AcornError::VersionConflict {
+                expected: None,
+                actual: self.version(branch, key),
+            });
+        }
+        self.put(branch, key, nut)
+    }
     /// Compare-and-set delete that enforces expected version when provided.
     fn delete_if_version(&self, _branch: &BranchId, _key: &str, _expected: Option<u64>) -> AcornResult<()> {
         Err(AcornError::NotImplemented)
     }
+    /// Make writes to `branch` durable before returning. In-memory trunks have nothing to flush and
+    /// keep this default, which returns `Ok(())`.
+    fn flush(&self, _branch: &BranchId) -> AcornResult<()> {
+        Ok(())
+    }
     fn capabilities(&self) -> &'static [TrunkCapability] {
         &[]
     }
@@ -112,6 +237,74 @@ pub trait TtlCleaner<T>: Trunk<T> {
     fn purge_expired(&self, branch: &BranchId) -> usize;
 }
 
+/// Background thread that calls [`TtlCleaner::purge_expired`] every `interval`; stops on drop.
+#[derive(Debug)]
+pub struct TtlSweeper {
+    stop: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl TtlSweeper {
+    /// Start sweeping `branch`. `on_cleanup` is called with the count after each sweep that removed entries.
+    pub fn spawn<T, S, F>(trunk: S, branch: BranchId, interval: Duration, on_cleanup: F) -> Self
+    where
+        S: TtlCleaner<T> + 'static,
+        F: Fn(usize) + Send + 'static,
+    {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let handle = thread::spawn(move || loop {
+            match stopped.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) => {
+                    let removed = trunk.purge_expired(&branch);
+                    if removed > 0 {
+                        tracing::debug!(branch = %branch, removed, "ttl sweep purged entries");
+                        on_cleanup(removed);
+                    }
+                }
+                Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+            }
+        });
+        TtlSweeper {
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for TtlSweeper {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Run `op` on a separate thread and wait at most `timeout` for its result.
+///
+/// On expiry this returns [`AcornError::Timeout`], but the thread is detached rather than stopped:
+/// `op` keeps running in the background and its result is dropped.
+pub fn with_timeout<T, F>(timeout: Duration, op: F) -> AcornResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> AcornResult<T> + Send + 'static,
+{
+    let (tx, rx) = mpsc::sync_channel(1);
+    thread::spawn(move || {
+        let _ = tx.send(op());
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => Err(AcornError::Timeout {
+            operation: "with_timeout",
+            duration_ms: timeout.as_millis() as u64,
+        }),
+        Err(RecvTimeoutError::Disconnected) => {
+            Err(AcornError::Trunk("with_timeout operation panicked".into()))
+        }
+    }
+}
+
 /// Capability flags for trunks; extend as behaviors are implemented.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TrunkCapability {
@@ -121,6 +314,9 @@ pub enum TrunkCapability {
     Streaming,
     Backpressure,
     Versions,
+    /// A key's version never repeats, even after it is deleted and written again, and `version`
+    /// stops reporting a value whose TTL has passed.
+    MonotonicVersions,
 }
 
 pub trait CapabilityAdvertiser {
@@ -136,6 +332,13 @@ pub enum HistoryEvent<T> {
 
 pub trait HistoryProvider<T>: Trunk<T> {
     fn history(&self, branch: &BranchId) -> AcornResult<Vec<HistoryEvent<T>>>;
+    /// Events after the first `start`, together with the total number of events. The default reads
+    /// the whole history; trunks that can skip the older events should override it.
+    fn history_since(&self, branch: &BranchId, start: usize) -> AcornResult<(Vec<HistoryEvent<T>>, usize)> {
+        let mut events = self.history(branch)?;
+        let total = events.len();
+        Ok((events.split_off(start.min(total)), total))
+    }
 }
 
 pub trait TtlProvider<T>: Trunk<T> {
@@ -148,6 +351,8 @@ pub trait NutCodec<T>: Send + Sync {
     fn decode(&self, bytes: &[u8]) -> AcornResult<T>;
 }
 
+/// JSON codec. With `T = Box<serde_json::value::RawValue>` payloads pass through verbatim,
+/// skipping the parse into a concrete type.
 #[derive(Debug, Clone)]
 pub struct JsonCodec;
 
@@ -156,11 +361,31 @@ where
     T: Serialize + DeserializeOwned,
 {
     fn encode(&self, value: &T) -> AcornResult<Vec<u8>> {
-        serde_json::to_vec(value).map_err(|e| AcornError::Serialization(e.to_string()))
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> AcornResult<T> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// MessagePack codec; more compact than JSON for numeric-heavy payloads.
+/// A trunk must be read with the codec that wrote it; mixing codecs on one trunk is unsupported.
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Clone)]
+pub struct MsgpackCodec;
+
+#[cfg(feature = "msgpack")]
+impl<T> NutCodec<T> for MsgpackCodec
+where
+    T: Serialize + DeserializeOwned,
+{
+    fn encode(&self, value: &T) -> AcornResult<Vec<u8>> {
+        rmp_serde::to_vec_named(value).map_err(|e| AcornError::Serialization(e.to_string()))
     }
 
     fn decode(&self, bytes: &[u8]) -> AcornResult<T> {
-        serde_json::from_slice(bytes).map_err(|e| AcornError::Serialization(e.to_string()))
+        rmp_serde::from_slice(bytes).map_err(|e| AcornError::Serialization(e.to_string()))
     }
 }
 
@@ -201,6 +426,13 @@ where
         self.trunk.put(&self.branch, key, nut)
     }
 
+    /// Store `nut` under a freshly generated ULID and return the key.
+    pub fn put_generated(&self, nut: Nut<T>) -> AcornResult<String> {
+        let key = id::ulid();
+        self.put(&key, nut)?;
+        Ok(key)
+    }
+
     pub fn put_if_version(&self, key: &str, expected: Option<u64>, nut: Nut<T>) -> AcornResult<()> {
         self.trunk.put_if_version(&self.branch, key, expected, nut)
     }
@@ -212,6 +444,67 @@ where
     pub fn delete_if_version(&self, key: &str, expected: Option<u64>) -> AcornResult<()> {
         self.trunk.delete_if_version(&self.branch, key, expected)
     }
+
+    /// Flush this tree's branch to durable storage; a no-op on in-memory trunks.
+    pub fn flush(&self) -> AcornResult<()> {
+        self.trunk.flush(&self.branch)
+    }
+}
+
+impl<T, S> Tree<T, S>
+where
+    T: Clone + Send + Sync + 'static,
+    S: Trunk<T> + Clone + Send + 'static,
+{
+    /// [`Tree::get`] bounded by `timeout`; see [`with_timeout`] for what happens to a read that overruns.
+    pub fn get_with_timeout(&self, key: &str, timeout: Duration) -> AcornResult<Option<Nut<T>>> {
+        let (tree, key) = (self.clone(), key.to_string());
+        with_timeout(timeout, move || tree.get(&key))
+    }
+}
+
+impl<T, S> Tree<T, S>
+where
+    T: Clone + PartialEq + Send + Sync + 'static,
+    S: Trunk<T> + Clone,
+{
+    /// Write `new` only if the current value equals `expected` (`None` meaning the key must be absent).
+    /// Returns whether the swap happened.
+    ///
+    /// The value is read between two version reads and re-read if the version moved, so the write is
+    /// conditioned on the version that belongs to the compared value. The swap is atomic only where
+    /// [`Trunk::put_if_version`] is; an absent key is claimed with [`Trunk::put_if_absent`], and on a
+    /// trunk without versions an existing value is replaced by a plain put after the comparison.
+    /// Returns `false` if the version keeps moving for [`MAX_UPDATE_ATTEMPTS`] reads.
+    pub fn compare_and_swap(&self, key: &str, expected: Option<&T>, new: T) -> AcornResult<bool> {
+        let mut attempts = 0;
+        let (version, current) = loop {
+            attempts += 1;
+            let version = self.trunk.version(&self.branch, key);
+            let current = self.get(key)?;
+            if self.trunk.version(&self.branch, key) == version {
+                break (version, current);
+            }
+            if attempts >= MAX_UPDATE_ATTEMPTS {
+                return Ok(false);
+            }
+        };
+        if current.as_ref().map(|nut| &nut.value) != expected {
+            return Ok(false);
+        }
+
+        let nut = Nut { value: new };
+        let result = match (version, current) {
+            (Some(version), _) => self.put_if_version(key, Some(version), nut),
+            (None, None) => self.trunk.put_if_absent(&self.branch, key, nut),
+            (None, Some(_)) => self.put(key, nut),
+        };
+        match result {
+            Ok(()) => Ok(true),
+            Err(AcornError::VersionConflict { .. }) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
 }
 
 impl<T, S> Tree<T, S>
@@ -222,8 +515,419 @@ where
     pub fn put_with_ttl(&self, key: &str, nut: Nut<T>, ttl: Ttl) -> AcornResult<()> {
         self.trunk.put_with_ttl(&self.branch, key, nut, ttl)
     }
+
+    /// Put a value that expires `duration` from now.
+    pub fn put_expiring_in(&self, key: &str, nut: Nut<T>, duration: Duration) -> AcornResult<()> {
+        self.put_with_ttl(key, nut, Ttl::from_now(duration))
+    }
+
+    /// Put a value that expires at the given instant.
+    pub fn put_expiring_at(&self, key: &str, nut: Nut<T>, when: SystemTime) -> AcornResult<()> {
+        self.put_with_ttl(key, nut, Ttl::at(when))
+    }
+}
+
+/// Kind of the most recent change to a key, as reported by [`Tree::keys_changed_since`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeType {
+    Put,
+    Delete,
+}
+
+/// Change totals reported by [`Tree::change_counts_since`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChangeCounts {
+    pub put: u64,
+    pub delete: u64,
+}
+
+impl<T, S> Tree<T, S>
+where
+    T: Clone + Send + Sync + 'static,
+    S: Trunk<T> + HistoryProvider<T> + Clone,
+{
+    /// Keys changed after the first `watermark` history events, each with its latest change and in
+    /// the order of that change. Also returns the watermark to pass on the next poll.
+    ///
+    /// The watermark is a position in the branch history, so it is only meaningful while the history
+    /// is append-only. After a rollback such as `MemoryTrunk::restore` it may point at different
+    /// events: a watermark past the end is rejected with [`AcornError::Configuration`], but one that
+    /// still fits goes undetected, so restart polling from 0 after a rollback.
+    pub fn keys_changed_since(&self, watermark: usize) -> AcornResult<(Vec<(String, ChangeType)>, usize)> {
+        let (events, next) = self.history_after(watermark)?;
+        let mut latest: HashMap<String, (usize, ChangeType)> = HashMap::new();
+        for (position, event) in events.into_iter().enumerate() {
+            let (key, change) = match event {
+                HistoryEvent::Put { key, .. } => (key, ChangeType::Put),
+                HistoryEvent::Delete { key } => (key, ChangeType::Delete),
+            };
+            latest.insert(key, (position, change));
+        }
+        let mut changes: Vec<_> = latest.into_iter().collect();
+        changes.sort_unstable_by_key(|(_, (position, _))| *position);
+        let changes = changes
+            .into_iter()
+            .map(|(key, (_, change))| (key, change))
+            .collect();
+        Ok((changes, next))
+    }
+
+    /// History events after `watermark` and the watermark for the next poll.
+    fn history_after(&self, watermark: usize) -> AcornResult<(Vec<HistoryEvent<T>>, usize)> {
+        let (events, total) = self.trunk.history_since(&self.branch, watermark)?;
+        if watermark > total {
+            return Err(AcornError::Configuration {
+                parameter: "watermark",
+                message: format!("watermark {watermark} is past the end of the history ({total} events)"),
+            });
+        }
+        Ok((events, total))
+    }
+
+    /// Number of puts and deletes after the first `watermark` history events, for rate calculations.
+    /// Unlike [`Tree::keys_changed_since`], repeated changes to one key are all counted; the watermark
+    /// has the same meaning and the same caveat about rollbacks.
+    pub fn change_counts_since(&self, watermark: usize) -> AcornResult<(ChangeCounts, usize)> {
+        let (events, next) = self.history_after(watermark)?;
+        let mut counts = ChangeCounts::default();
+        for event in &events {
+            match event {
+                HistoryEvent::Put { .. } => counts.put += 1,
+                HistoryEvent::Delete { .. } => counts.delete += 1,
+            }
+        }
+        Ok((counts, next))
+    }
+}
+
+/// Progress reported by the bulk operations' `*_with_progress` variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub done: u64,
+    pub total: Option<u64>,
+}
+
+/// Records processed between progress callbacks; a final callback always follows the last record.
+const PROGRESS_INTERVAL: u64 = 100;
+
+/// One line of a tree dump: a JSON object per record, newline-delimited.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(bound(serialize = "T: Serialize", deserialize = "T: DeserializeOwned"))]
+pub struct ExportRecord<T> {
+    pub key: String,
+    pub value: T,
+}
+
+/// Iterator returned by [`Tree::iter`].
+pub struct TreeIter<'a, T, S: Trunk<T>> {
+    tree: &'a Tree<T, S>,
+    keys: std::vec::IntoIter<String>,
+}
+
+impl<T, S> Iterator for TreeIter<'_, T, S>
+where
+    T: Clone + Send + Sync + 'static,
+    S: Trunk<T> + Clone,
+{
+    type Item = AcornResult<(String, Nut<T>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for key in self.keys.by_ref() {
+            match self.tree.get(&key) {
+                Ok(Some(nut)) => return Some(Ok((key, nut))),
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        None
+    }
+
+    /// At most the number of keys not yet visited. Keys deleted after [`Tree::iter`] listed them are
+    /// skipped, so the lower bound stays 0.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.keys.len()))
+    }
+}
+
+impl<T, S> TreeIter<'_, T, S>
+where
+    S: Trunk<T>,
+{
+    /// Keys listed up front that have not been visited yet; an upper bound on the items remaining,
+    /// e.g. for progress reporting. Since the lower bound of `size_hint` is 0, `collect` does not
+    /// pre-allocate from it.
+    pub fn remaining_hint(&self) -> usize {
+        self.keys.len()
+    }
+}
+
+impl<'a, T, S> IntoIterator for &'a Tree<T, S>
+where
+    T: Clone + Send + Sync + 'static,
+    S: Trunk<T> + KeyedTrunk<T> + Clone,
+{
+    type Item = AcornResult<(String, Nut<T>)>;
+    type IntoIter = TreeIter<'a, T, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Filtering and transformation applied by [`Tree::copy_to`].
+pub struct CopyOptions<T> {
+    /// Replace keys that already exist in the destination.
+    pub overwrite: bool,
+    /// Only copy keys starting with this prefix.
+    pub key_prefix: Option<String>,
+    /// Rewrite each value before it is written; returning `None` skips the key.
+    pub transform: Option<Box<dyn Fn(&str, T) -> Option<T>>>,
+}
+
+impl<T> Default for CopyOptions<T> {
+    fn default() -> Self {
+        CopyOptions {
+            overwrite: true,
+            key_prefix: None,
+            transform: None,
+        }
+    }
+}
+
+/// Counts reported by [`Tree::copy_to`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CopyReport {
+    pub copied: u64,
+    pub skipped: u64,
+    pub transformed: u64,
+}
+
+impl<T, S> Tree<T, S>
+where
+    T: Clone + Send + Sync + 'static,
+    S: Trunk<T> + KeyedTrunk<T> + Clone,
+{
+    /// Iterate the branch's records in key order. Keys are listed up front; values are read lazily,
+    /// and keys deleted mid-iteration are skipped.
+    pub fn iter(&self) -> TreeIter<'_, T, S> {
+        let mut keys = self.trunk.keys(&self.branch);
+        keys.sort();
+        TreeIter {
+            tree: self,
+            keys: keys.into_iter(),
+        }
+    }
+
+    /// Like [`Tree::iter`], restricted to keys starting with `prefix`; other values are never read.
+    pub fn iter_prefix(&self, prefix: &str) -> TreeIter<'_, T, S> {
+        let mut keys: Vec<String> = self
+            .trunk
+            .keys(&self.branch)
+            .into_iter()
+            .filter(|key| key.starts_with(prefix))
+            .collect();
+        keys.sort();
+        TreeIter {
+            tree: self,
+            keys: keys.into_iter(),
+        }
+    }
+
+    /// Count keys starting with `prefix` from the key listing alone, without reading values.
+    /// Entries past their TTL are counted until they are purged.
+    pub fn count_prefix(&self, prefix: &str) -> usize {
+        self.trunk
+            .keys(&self.branch)
+            .iter()
+            .filter(|key| key.starts_with(prefix))
+            .count()
+    }
+
+    /// Delete every key starting with `prefix`, returning the number of keys removed.
+    pub fn delete_prefix(&self, prefix: &str) -> AcornResult<u64> {
+        let mut removed = 0u64;
+        for key in self.trunk.keys(&self.branch) {
+            if !key.starts_with(prefix) {
+                continue;
+            }
+            match self.delete(&key) {
+                Ok(()) => removed += 1,
+                // Already gone (expired or deleted concurrently); nothing to count.
+                Err(AcornError::MissingKey(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Delete every key in the branch, returning the number of keys removed.
+    pub fn clear(&self) -> AcornResult<u64> {
+        self.delete_prefix("")
+    }
+
+    /// Copy records from this tree's branch into `dest`, applying the prefix filter and transform.
+    pub fn copy_to<D>(&self, dest: &Tree<T, D>, options: CopyOptions<T>) -> AcornResult<CopyReport>
+    where
+        D: Trunk<T> + Clone,
+    {
+        self.copy_to_with_progress(dest, options, &|_| {})
+    }
+
+    /// [`Tree::copy_to`] reporting progress over the keys visited (copied or skipped).
+    pub fn copy_to_with_progress<D>(
+        &self,
+        dest: &Tree<T, D>,
+        options: CopyOptions<T>,
+        progress: &dyn Fn(Progress),
+    ) -> AcornResult<CopyReport>
+    where
+        D: Trunk<T> + Clone,
+    {
+        let mut keys = self.trunk.keys(&self.branch);
+        keys.sort();
+        if let Some(prefix) = &options.key_prefix {
+            keys.retain(|key| key.starts_with(prefix.as_str()));
+        }
+        let total = Some(keys.len() as u64);
+
+        let mut report = CopyReport::default();
+        let mut visited = 0u64;
+        for key in keys {
+            visited += 1;
+            if visited % PROGRESS_INTERVAL == 0 {
+                progress(Progress { done: visited, total });
+            }
+            let Some(nut) = self.get(&key)? else {
+                continue;
+            };
+            if !options.overwrite && dest.get(&key)?.is_some() {
+                report.skipped += 1;
+                continue;
+            }
+            let value = match &options.transform {
+                Some(transform) => match transform(key.as_str(), nut.value) {
+                    Some(value) => {
+                        report.transformed += 1;
+                        value
+                    }
+                    None => {
+                        report.skipped += 1;
+                        continue;
+                    }
+                },
+                None => nut.value,
+            };
+            dest.put(&key, Nut { value })?;
+            report.copied += 1;
+        }
+        if visited % PROGRESS_INTERVAL != 0 || visited == 0 {
+            progress(Progress { done: visited, total });
+        }
+        Ok(report)
+    }
 }
 
+impl<T, S> Tree<T, S>
+where
+    T: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+    S: Trunk<T> + KeyedTrunk<T> + Clone,
+{
+    /// Write every record in the branch as newline-delimited [`ExportRecord`]s, sorted by key.
+    /// Returns the number of records written.
+    pub fn export_all<W: Write>(&self, writer: W) -> AcornResult<u64> {
+        self.export_all_with_progress(writer, &|_| {})
+    }
+
+    /// [`Tree::export_all`] reporting progress over the records written; `total` is the number of
+    /// keys listed up front.
+    pub fn export_all_with_progress<W: Write>(
+        &self,
+        mut writer: W,
+        progress: &dyn Fn(Progress),
+    ) -> AcornResult<u64> {
+        let mut keys = self.trunk.keys(&self.branch);
+        keys.sort();
+        let total = Some(keys.len() as u64);
+
+        let mut count = 0u64;
+        for key in keys {
+            if let Some(nut) = self.get(&key)? {
+                let record = ExportRecord {
+                    key,
+                    value: nut.value,
+                };
+                serde_json::to_writer(&mut writer, &record)?;
+                writer.write_all(b"\n").with_operation("export_all")?;
+                count += 1;
+                if count % PROGRESS_INTERVAL == 0 {
+                    progress(Progress { done: count, total });
+                }
+            }
+        }
+        writer.flush().with_operation("export_all")?;
+        if count % PROGRESS_INTERVAL != 0 || count == 0 {
+            progress(Progress { done: count, total });
+        }
+        Ok(count)
+    }
+
+    /// Put every record from a dump produced by [`Tree::export_all`], reading line by line.
+    /// Returns the number of records imported.
+    pub fn import_all<R: Read>(&self, reader: R) -> AcornResult<u64> {
+        self.import_all_with_progress(reader, &|_| {})
+    }
+
+    /// [`Tree::import_all`] reporting progress over the records imported. The dump is streamed, so
+    /// `total` is always `None`.
+    pub fn import_all_with_progress<R: Read>(
+        &self,
+        reader: R,
+        progress: &dyn Fn(Progress),
+    ) -> AcornResult<u64> {
+        let mut count = 0u64;
+        for line in BufReader::new(reader).lines() {
+            let line = line.with_operation("import_all")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: ExportRecord<T> = serde_json::from_str(&line)?;
+            self.put(&record.key, Nut { value: record.value })?;
+            count += 1;
+            if count % PROGRESS_INTERVAL == 0 {
+                progress(Progress {
+                    done: count,
+                    total: None,
+                });
+            }
+        }
+        if count % PROGRESS_INTERVAL != 0 || count == 0 {
+            progress(Progress {
+                done: count,
+                total: None,
+            });
+        }
+        Ok(count)
+    }
+}
+
+impl<T, S> Tree<T, S>
+where
+    T: Clone + Send + Sync + 'static,
+    S: Trunk<T> + TtlCleaner<T> + Clone + 'static,
+{
+    /// Purge this branch's expired entries in the background until the returned sweeper is dropped.
+    pub fn spawn_ttl_sweeper<F>(&self, interval: Duration, on_cleanup: F) -> TtlSweeper
+    where
+        F: Fn(usize) + Send + 'static,
+    {
+        TtlSweeper::spawn::<T, S, F>(self.trunk.clone(), self.branch.clone(), interval, on_cleanup)
+    }
+}
+
+/// How many times [`EncodedTree::upsert_with`] and its siblings re-read and retry after losing a
+/// race before returning the conflict.
+pub const MAX_UPDATE_ATTEMPTS: u32 = 64;
+
 /// Tree wrapper that encodes/decodes typed payloads to byte-oriented trunks.
 #[derive(Debug, Clone)]
 pub struct EncodedTree<T, S, C>
@@ -269,6 +973,213 @@ where
     pub fn delete(&self, key: &str) -> AcornResult<()> {
         self.tree.delete(key)
     }
+
+    /// Replace the value at `key` with `f(current)`, creating it when absent, and return what was stored.
+    /// When another writer changes the key between the read and the write, `f` runs again on the new
+    /// value, so it may be called more than once and should not have side effects. Whether that
+    /// catches every concurrent writer depends on [`Trunk::put_if_version`]. Gives up with
+    /// [`AcornError::VersionConflict`] after [`MAX_UPDATE_ATTEMPTS`] lost races.
+    pub fn upsert_with<F>(&self, key: &str, mut f: F) -> AcornResult<T>
+    where
+        F: FnMut(Option<T>) -> T,
+    {
+        self.update_with(key, |current| Ok(f(current)))
+    }
+
+    /// Read-modify-write `key`, retrying whenever a concurrent writer bumps the version in between.
+    fn update_with<F>(&self, key: &str, mut f: F) -> AcornResult<T>
+    where
+        F: FnMut(Option<T>) -> AcornResult<T>,
+    {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let version = self.tree.trunk().version(self.tree.branch(), key);
+            let current = self.get(key)?.map(|nut| nut.value);
+            let exists = current.is_some();
+            let next = f(current)?;
+            let nut = Nut {
+                value: self.codec.encode(&next)?,
+            };
+            let result = match (version, exists) {
+                (Some(version), _) => self.tree.put_if_version(key, Some(version), nut),
+                (None, false) => self.tree.trunk().put_if_absent(self.tree.branch(), key, nut),
+                (None, true) => self.tree.put(key, nut),
+            };
+            match result {
+                Ok(()) => return Ok(next),
+                Err(AcornError::VersionConflict { .. }) if attempts < MAX_UPDATE_ATTEMPTS => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl<T, S, C> EncodedTree<T, S, C>
+where
+    T: Clone,
+    S: Trunk<Vec<u8>> + KeyedTrunk<Vec<u8>> + Clone,
+    C: NutCodec<T> + Clone,
+{
+    /// Decode the values under `prefix` one at a time, in key order. The iterator borrows the tree
+    /// and holds only the key listing and the current item, never the decoded values already yielded.
+    pub fn values_stream<'a>(&'a self, prefix: &str) -> impl Iterator<Item = AcornResult<T>> + 'a {
+        self.tree
+            .iter_prefix(prefix)
+            .map(|item| item.and_then(|(_, nut)| self.codec.decode(&nut.value)))
+    }
+}
+
+impl<S, C> EncodedTree<i64, S, C>
+where
+    S: Trunk<Vec<u8>> + Clone,
+    C: NutCodec<i64> + Clone,
+{
+    /// Add `delta` to the counter at `key`, treating a missing key as 0, and return the new value.
+    /// Fails with [`AcornError::Overflow`] instead of wrapping or saturating. An increment that loses a
+    /// race is re-applied on top of the winner, so no increment is lost wherever
+    /// [`Trunk::put_if_version`] is atomic.
+    pub fn increment(&self, key: &str, delta: i64) -> AcornResult<i64> {
+        self.update_with(key, |current| {
+            current
+                .unwrap_or(0)
+                .checked_add(delta)
+                .ok_or_else(|| AcornError::Overflow { key: key.to_string() })
+        })
+    }
+}
+
+impl<S, C> EncodedTree<serde_json::Value, S, C>
+where
+    S: Trunk<Vec<u8>> + Clone,
+    C: NutCodec<serde_json::Value> + Clone,
+{
+    /// Deep-merge `patch` into the document at `key` (see [`json::deep_merge`]), creating it when
+    /// absent. If the stored value is not an object it is replaced by `patch`.
+    pub fn merge(&self, key: &str, patch: &serde_json::Value) -> AcornResult<()> {
+        self.update_with(key, |current| {
+            let mut doc = current.unwrap_or(serde_json::Value::Null);
+            json::deep_merge(&mut doc, patch);
+            Ok(doc)
+        })?;
+        Ok(())
+    }
+
+    /// Read the field at `path` (see [`jsonpath::get`]) from the document at `key`.
+    pub fn get_field(&self, key: &str, path: &str) -> AcornResult<Option<serde_json::Value>> {
+        Ok(self
+            .get(key)?
+            .and_then(|nut| jsonpath::get(&nut.value, path).cloned()))
+    }
+
+    /// Apply an RFC 7386 merge patch to the document at `key`, creating it when absent.
+    pub fn apply_merge_patch(&self, key: &str, patch: &serde_json::Value) -> AcornResult<()> {
+        self.update_with(key, |current| {
+            let mut doc = current.unwrap_or(serde_json::Value::Null);
+            json::merge_patch(&mut doc, patch);
+            Ok(doc)
+        })?;
+        Ok(())
+    }
+
+    /// Apply RFC 6902 operations to the document at `key`. If any operation fails, including a
+    /// `test`, nothing is written.
+    pub fn apply_json_patch(&self, key: &str, ops: &[json::PatchOp]) -> AcornResult<()> {
+        self.update_with(key, |current| {
+            let mut doc = current.ok_or_else(|| AcornError::MissingKey(key.to_string()))?;
+            json::apply_patch(&mut doc, ops)?;
+            Ok(doc)
+        })?;
+        Ok(())
+    }
+}
+
+/// Read-through cache of decoded values over an [`EncodedTree`].
+///
+/// Entries remember the trunk version they were read at and are refreshed when it moves, so writes
+/// made through other handles on the same trunk are not served stale. That only holds when versions
+/// never repeat, so trunks that don't advertise [`TrunkCapability::MonotonicVersions`] are read
+/// through without caching.
+pub struct CachedTree<T, S, C>
+where
+    S: Trunk<Vec<u8>>,
+    C: NutCodec<T>,
+{
+    tree: EncodedTree<T, S, C>,
+    cache: Mutex<LruCache<String, (u64, Arc<T>)>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<T, S, C> CachedTree<T, S, C>
+where
+    T: Clone,
+    S: Trunk<Vec<u8>> + Clone,
+    C: NutCodec<T> + Clone,
+{
+    pub fn new(tree: EncodedTree<T, S, C>, capacity: usize) -> Self {
+        CachedTree {
+            tree,
+            cache: Mutex::new(LruCache::new(capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> AcornResult<Option<Arc<T>>> {
+        let trunk = self.tree.tree.trunk();
+        let version = if trunk.capabilities().contains(&TrunkCapability::MonotonicVersions) {
+            trunk.version(self.tree.tree.branch(), key)
+        } else {
+            None
+        };
+        if let Some(version) = version {
+            let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some((cached_version, value)) = cache.get(&key.to_string()) {
+                if *cached_version == version {
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    return Ok(Some(value.clone()));
+                }
+            }
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let value = self.tree.get(key)?.map(|nut| Arc::new(nut.value));
+        if let (Some(version), Some(value)) = (version, &value) {
+            self.cache
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .put(key.to_string(), (version, value.clone()));
+        }
+        Ok(value)
+    }
+
+    pub fn put(&self, key: &str, nut: Nut<T>) -> AcornResult<()> {
+        self.tree.put(key, nut)?;
+        self.invalidate(key);
+        Ok(())
+    }
+
+    pub fn delete(&self, key: &str) -> AcornResult<()> {
+        self.tree.delete(key)?;
+        self.invalidate(key);
+        Ok(())
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    fn invalidate(&self, key: &str) {
+        self.cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&key.to_string());
+    }
 }
 
 /// Minimal LRU cache skeleton for helpers/tests.
@@ -308,6 +1219,14 @@ where
         self.map.get(key)
     }
 
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let removed = self.map.remove(key)?;
+        if let Some(pos) = self.order.iter().position(|k| k == key) {
+            self.order.remove(pos);
+        }
+        Some(removed)
+    }
+
     fn touch(&mut self, key: &K) {
         if let Some(pos) = self.order.iter().position(|k| k == key) {
             self.order.remove(pos);
@@ -349,4 +1268,76 @@ mod tests {
         let decoded: Demo = codec.decode(&bytes).unwrap();
         assert_eq!(decoded, original);
     }
+
+    #[test]
+    fn io_errors_keep_their_source() {
+        use std::error::Error as _;
+
+        let failing: std::io::Result<()> = Err(std::io::Error::new(std::io::ErrorKind::NotFound, "gone"));
+        let err = failing.with_operation("read_nut").unwrap_err();
+
+        assert_eq!(err.operation(), Some("read_nut"));
+        let source = err.source().unwrap().downcast_ref::<std::io::Error>().unwrap();
+        assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn io_errors_capture_a_backtrace_when_enabled() {
+        let failing: std::io::Result<()> = Err(std::io::Error::other("disk on fire"));
+        let err = failing.with_operation("flush").unwrap_err();
+
+        let enabled = Backtrace::capture().status() == BacktraceStatus::Captured;
+        assert_eq!(err.backtrace().is_some(), enabled);
+        assert!(AcornError::MissingKey("k".into()).backtrace().is_none());
+    }
+
+    #[test]
+    fn with_timeout_gives_up_on_slow_operations() {
+        let slow = with_timeout(Duration::from_millis(20), || {
+            thread::sleep(Duration::from_millis(500));
+            Ok(1)
+        });
+        assert!(matches!(
+            slow,
+            Err(AcornError::Timeout {
+                operation: "with_timeout",
+                duration_ms: 20
+            })
+        ));
+
+        let fast = with_timeout(Duration::from_secs(5), || Ok(2));
+        assert_eq!(fast.unwrap(), 2);
+    }
+
+    #[test]
+    fn json_errors_convert_with_question_mark() {
+        fn parse(raw: &str) -> AcornResult<Demo> {
+            Ok(serde_json::from_str(raw)?)
+        }
+
+        assert!(matches!(parse("{not json"), Err(AcornError::Serialization(_))));
+    }
+
+    #[test]
+    fn json_codec_passes_raw_values_through() {
+        let raw = serde_json::value::RawValue::from_string(r#"{ "b":1,  "a":[true] }"#.into()).unwrap();
+
+        let bytes = JsonCodec.encode(&raw).unwrap();
+        let decoded: Box<serde_json::value::RawValue> = JsonCodec.decode(&bytes).unwrap();
+        assert_eq!(decoded.get(), raw.get());
+        assert_eq!(bytes, raw.get().as_bytes());
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn msgpack_codec_round_trip_differs_from_json() {
+        let original = Demo {
+            value: "hello".into(),
+        };
+
+        let packed = NutCodec::<Demo>::encode(&MsgpackCodec, &original).unwrap();
+        let decoded: Demo = MsgpackCodec.decode(&packed).unwrap();
+        assert_eq!(decoded, original);
+        assert_ne!(packed, NutCodec::<Demo>::encode(&JsonCodec, &original).unwrap());
+    }
 }
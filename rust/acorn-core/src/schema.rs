@@ -0,0 +1,92 @@
+//! JSON Schema validation of stored values, as a wrapper around another [`NutCodec`].
+
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{AcornError, AcornResult, NutCodec};
+
+/// Codec that checks every value against a JSON Schema before `inner` encodes it; decoding is
+/// passed straight through. Wrap the codec a tree already uses, e.g. `SchemaCodec::new(&schema, JsonCodec)`.
+#[derive(Clone)]
+pub struct SchemaCodec<C> {
+    validator: Arc<jsonschema::Validator>,
+    inner: C,
+}
+
+impl<C> SchemaCodec<C> {
+    /// Compile `schema`; a schema that does not compile is an [`AcornError::Configuration`].
+    pub fn new(schema: &Value, inner: C) -> AcornResult<Self> {
+        let validator = jsonschema::validator_for(schema).map_err(|e| AcornError::Configuration {
+            parameter: "schema",
+            message: e.to_string(),
+        })?;
+        Ok(SchemaCodec {
+            validator: Arc::new(validator),
+            inner,
+        })
+    }
+
+    /// Check `value` without encoding it, reporting the first violation and where it occurred.
+    pub fn validate<T: Serialize>(&self, value: &T) -> AcornResult<()> {
+        let doc = serde_json::to_value(value)?;
+        match self.validator.iter_errors(&doc).next() {
+            Some(error) => Err(AcornError::SchemaViolation {
+                path: error.instance_path.to_string(),
+                message: error.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<C: fmt::Debug> fmt::Debug for SchemaCodec<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SchemaCodec")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, C> NutCodec<T> for SchemaCodec<C>
+where
+    T: Serialize + DeserializeOwned,
+    C: NutCodec<T>,
+{
+    fn encode(&self, value: &T) -> AcornResult<Vec<u8>> {
+        self.validate(value)?;
+        self.inner.encode(value)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> AcornResult<T> {
+        self.inner.decode(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JsonCodec;
+    use serde_json::json;
+
+    #[test]
+    fn rejects_missing_required_field_and_stores_valid_document() {
+        let schema = json!({
+            "type": "object",
+            "required": ["name"],
+            "properties": {"name": {"type": "string"}, "tags": {"type": "array"}}
+        });
+        let codec = SchemaCodec::new(&schema, JsonCodec).unwrap();
+
+        let invalid = json!({"tags": []});
+        let err = NutCodec::<Value>::encode(&codec, &invalid).unwrap_err();
+        assert!(matches!(err, AcornError::SchemaViolation { .. }));
+        assert!(codec.validate(&json!({"name": 7})).is_err());
+
+        let valid = json!({"name": "acorn", "tags": ["a"]});
+        let bytes = NutCodec::<Value>::encode(&codec, &valid).unwrap();
+        assert_eq!(NutCodec::<Value>::decode(&codec, &bytes).unwrap(), valid);
+    }
+}
@@ -1,11 +1,13 @@
 #![allow(dead_code)]
 
-use acorn_core::{AcornError, AcornResult, BranchId, KeyedTrunk, Tree, Trunk};
+use acorn_core::{AcornError, AcornResult, BranchId, KeyedTrunk, Nut, Tree, Trunk};
 use serde::{de::DeserializeOwned, Serialize};
 #[cfg(feature = "http-client")]
 use serde_json;
 #[cfg(feature = "http-client")]
 use std::collections::{HashMap, HashSet};
+#[cfg(feature = "http-client")]
+use std::time::Duration;
 use tracing::instrument;
 
 /// HTTP/WebSocket sync endpoint target.
@@ -23,6 +25,7 @@ pub enum SyncError {
     Storage(String),
     VersionConflict(String),
     MissingKey(String),
+    Timeout(String),
     Unknown(String),
 }
 
@@ -32,11 +35,36 @@ pub trait SyncTransport {
     fn pull(&self, branch: &BranchId) -> Result<SyncPullResponse, SyncError>;
 }
 
+/// Timeout and retry controls for [`HttpTransport`].
+#[cfg(feature = "http-client")]
+#[derive(Debug, Clone)]
+pub struct HttpTransportOptions {
+    /// Per-request deadline; `None` waits indefinitely.
+    pub timeout: Option<Duration>,
+    /// Extra attempts made after a connection failure, or after a timeout for `pull`. Timed-out
+    /// `apply` requests are not retried, since the server may already have applied the batch.
+    pub retries: u32,
+    /// Base delay between attempts, multiplied by the attempt number.
+    pub backoff: Duration,
+}
+
+#[cfg(feature = "http-client")]
+impl Default for HttpTransportOptions {
+    fn default() -> Self {
+        HttpTransportOptions {
+            timeout: None,
+            retries: 0,
+            backoff: Duration::from_millis(200),
+        }
+    }
+}
+
 #[cfg(feature = "http-client")]
 #[derive(Clone)]
 pub struct HttpTransport {
     client: reqwest::Client,
     base_url: String,
+    options: HttpTransportOptions,
 }
 
 #[cfg(feature = "http-client")]
@@ -45,22 +73,73 @@ impl HttpTransport {
         HttpTransport {
             client: reqwest::Client::new(),
             base_url: base_url.into(),
+            options: HttpTransportOptions::default(),
         }
     }
 
-    fn block_on<F, T>(&self, fut: F) -> Result<T, SyncError>
-    where
-        F: std::future::Future<Output = Result<T, reqwest::Error>>,
-    {
+    pub fn with_options<T: Into<String>>(
+        base_url: T,
+        options: HttpTransportOptions,
+    ) -> Result<Self, SyncError> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = options.timeout {
+            builder = builder.timeout(timeout);
+        }
+        let client = builder.build().map_err(|e| SyncError::Network(e.to_string()))?;
+        Ok(HttpTransport {
+            client,
+            base_url: base_url.into(),
+            options,
+        })
+    }
+
+    fn block_on<F: std::future::Future>(&self, fut: F) -> Result<F::Output, SyncError> {
         if let Ok(handle) = tokio::runtime::Handle::try_current() {
-            handle
-                .block_on(fut)
-                .map_err(|e| SyncError::Network(e.to_string()))
+            Ok(handle.block_on(fut))
         } else {
-            tokio::runtime::Runtime::new()
+            Ok(tokio::runtime::Runtime::new()
                 .map_err(|e| SyncError::Network(e.to_string()))?
-                .block_on(fut)
-                .map_err(|e| SyncError::Network(e.to_string()))
+                .block_on(fut))
+        }
+    }
+
+    /// Run `send` until it succeeds, retrying up to `options.retries` times. Connection failures are
+    /// always retried since nothing reached the server; timeouts only when the request is `idempotent`.
+    fn send_with_retry<F, Fut, T>(
+        &self,
+        operation: &str,
+        idempotent: bool,
+        mut send: F,
+    ) -> Result<T, SyncError>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, reqwest::Error>>,
+    {
+        let mut attempt = 0u32;
+        loop {
+            match self.block_on(send())? {
+                Ok(value) => return Ok(value),
+                Err(e)
+                    if attempt < self.options.retries
+                        && (e.is_connect() || (idempotent && e.is_timeout())) =>
+                {
+                    attempt += 1;
+                    tracing::debug!(operation, attempt, error = %e, "retrying sync request");
+                    std::thread::sleep(self.options.backoff * attempt);
+                }
+                Err(e) => return Err(self.classify(operation, e)),
+            }
+        }
+    }
+
+    fn classify(&self, operation: &str, error: reqwest::Error) -> SyncError {
+        if error.is_timeout() {
+            let duration_ms = self.options.timeout.map(|t| t.as_millis()).unwrap_or_default();
+            SyncError::Timeout(format!("{} timed out after {}ms", operation, duration_ms))
+        } else if error.is_status() || error.is_decode() {
+            SyncError::Protocol(error.to_string())
+        } else {
+            SyncError::Network(error.to_string())
         }
     }
 }
@@ -69,20 +148,32 @@ impl HttpTransport {
 impl SyncTransport for HttpTransport {
     fn apply(&self, request: &SyncApplyRequest) -> Result<SyncApplyResponse, SyncError> {
         let url = format!("{}/sync/apply", self.base_url);
-        self.block_on(self.client.post(url).json(request).send())?
-            .error_for_status()
-            .map_err(|e| SyncError::Protocol(e.to_string()))?
-            .json::<SyncApplyResponse>()
-            .map_err(|e| SyncError::Protocol(e.to_string()))
+        let (client, url) = (&self.client, url.as_str());
+        self.send_with_retry("sync_apply", false, || async move {
+            client
+                .post(url)
+                .json(request)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<SyncApplyResponse>()
+                .await
+        })
     }
 
     fn pull(&self, branch: &BranchId) -> Result<SyncPullResponse, SyncError> {
         let url = format!("{}/sync/pull", self.base_url);
-        self.block_on(self.client.get(url).query(&[("branch", branch.as_str())]).send())?
-            .error_for_status()
-            .map_err(|e| SyncError::Protocol(e.to_string()))?
-            .json::<SyncPullResponse>()
-            .map_err(|e| SyncError::Protocol(e.to_string()))
+        let (client, url) = (&self.client, url.as_str());
+        self.send_with_retry("sync_pull", true, || async move {
+            client
+                .get(url)
+                .query(&[("branch", branch.as_str())])
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<SyncPullResponse>()
+                .await
+        })
     }
 }
 
@@ -127,38 +218,12 @@ impl SyncClient {
     pub async fn pull<T, S>(&self, tree: &Tree<T, S>, endpoint: &SyncEndpoint) -> AcornResult<SyncResult>
     where
         T: Clone + Send + Sync + 'static + std::fmt::Debug + Serialize + DeserializeOwned,
-        S: Trunk<T> + Send + Sync,
+        S: Trunk<T> + Clone + Send + Sync,
     {
         #[cfg(feature = "http-client")]
         {
             let transport = HttpTransport::new(endpoint.url.clone());
-            self.pull_with_transport(&transport, &endpoint.branch)
-                .and_then(|resp| {
-                    for key in resp.deleted {
-                        let _ = tree.delete(&key)?;
-                    }
-
-                    let mut applied = 0usize;
-                    for op in resp.batch.operations {
-                        match op {
-                            SyncMutation::Put { key, value, .. } => {
-                                let decoded: T = serde_json::from_slice(&value)
-                                    .map_err(|e| AcornError::Serialization(e.to_string()))?;
-                                tree.put(&key, Nut { value: decoded })?;
-                                applied += 1;
-                            }
-                            SyncMutation::Delete { key, .. } => {
-                                let _ = tree.delete(&key)?;
-                            }
-                        }
-                    }
-
-                    Ok(SyncResult {
-                        applied,
-                        conflicts: 0,
-                        conflict_keys: Vec::new(),
-                    })
-                })
+            self.pull_into_with_transport(&transport, tree, &endpoint.branch)
         }
         #[cfg(not(feature = "http-client"))]
         {
@@ -209,8 +274,7 @@ impl SyncClient {
                     continue;
                 }
                 if let Some(nut) = tree.get(&key)? {
-                    let bytes = serde_json::to_vec(&nut.value)
-                        .map_err(|e| AcornError::Serialization(e.to_string()))?;
+                    let bytes = serde_json::to_vec(&nut.value)?;
                     ops.push(SyncMutation::Put {
                         key: key.clone(),
                         value: bytes,
@@ -282,8 +346,7 @@ impl SyncClient {
         let mut ops = Vec::new();
         for key in tree.trunk().keys(branch) {
             if let Some(nut) = tree.get(&key)? {
-                let bytes =
-                    serde_json::to_vec(&nut.value).map_err(|e| AcornError::Serialization(e.to_string()))?;
+                let bytes = serde_json::to_vec(&nut.value)?;
                 ops.push(SyncMutation::Put {
                     key: key.clone(),
                     value: bytes,
@@ -305,6 +368,50 @@ impl SyncClient {
         })
     }
 
+    /// Pull `branch` through `transport` and apply it to `tree`. Remote tombstones are deleted locally
+    /// (keys already absent are skipped) and every remote value replaces the local one, so the remote
+    /// side wins. `applied` counts the values written.
+    pub fn pull_into_with_transport<T, S, X>(
+        &self,
+        transport: &X,
+        tree: &Tree<T, S>,
+        branch: &BranchId,
+    ) -> AcornResult<SyncResult>
+    where
+        T: Clone + Send + Sync + 'static + std::fmt::Debug + Serialize + DeserializeOwned,
+        S: Trunk<T> + Clone,
+        X: SyncTransport,
+    {
+        let resp = self.pull_with_transport(transport, branch)?;
+        for key in resp.deleted {
+            match tree.delete(&key) {
+                Ok(()) | Err(AcornError::MissingKey(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut applied = 0usize;
+        for op in resp.batch.operations {
+            match op {
+                SyncMutation::Put { key, value, .. } => {
+                    let decoded: T = serde_json::from_slice(&value)?;
+                    tree.put(&key, Nut { value: decoded })?;
+                    applied += 1;
+                }
+                SyncMutation::Delete { key, .. } => match tree.delete(&key) {
+                    Ok(()) | Err(AcornError::MissingKey(_)) => {}
+                    Err(e) => return Err(e),
+                },
+            }
+        }
+
+        Ok(SyncResult {
+            applied,
+            conflicts: 0,
+            conflict_keys: Vec::new(),
+        })
+    }
+
     /// Pull mutations using the provided transport.
     pub fn pull_with_transport<T: SyncTransport>(
         &self,
@@ -788,6 +895,75 @@ mod tests {
         assert_eq!(result.conflict_keys, vec!["key".to_string()]);
     }
 
+    #[cfg(feature = "http-client")]
+    #[test]
+    fn http_transport_times_out_against_silent_server() {
+        use std::time::{Duration, Instant};
+
+        // Accepts connections (via the backlog) but never answers.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let transport = HttpTransport::with_options(
+            format!("http://{}", addr),
+            HttpTransportOptions {
+                timeout: Some(Duration::from_millis(100)),
+                retries: 1,
+                backoff: Duration::from_millis(10),
+            },
+        )
+        .unwrap();
+
+        let started = Instant::now();
+        let result = transport.pull(&BranchId::new("main"));
+        assert!(matches!(result, Err(SyncError::Timeout(_))));
+        assert!(started.elapsed() < Duration::from_secs(2));
+        drop(listener);
+    }
+
+    #[cfg(feature = "http-client")]
+    #[test]
+    fn http_transport_retries_timed_out_pulls_but_not_applies() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+
+        // Counts connections and holds them open without ever answering.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+        std::thread::spawn(move || {
+            let mut held = Vec::new();
+            for stream in listener.incoming() {
+                counter.fetch_add(1, Ordering::SeqCst);
+                held.push(stream);
+            }
+        });
+        let transport = HttpTransport::with_options(
+            format!("http://{}", addr),
+            HttpTransportOptions {
+                timeout: Some(Duration::from_millis(100)),
+                retries: 2,
+                backoff: Duration::from_millis(10),
+            },
+        )
+        .unwrap();
+
+        let request = SyncApplyRequest {
+            batch: SyncBatch {
+                branch: BranchId::new("main"),
+                operations: vec![],
+            },
+        };
+        assert!(matches!(transport.apply(&request), Err(SyncError::Timeout(_))));
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        assert!(matches!(
+            transport.pull(&BranchId::new("main")),
+            Err(SyncError::Timeout(_))
+        ));
+        assert_eq!(connections.load(Ordering::SeqCst), 4);
+    }
+
     #[cfg(feature = "http-client")]
     #[tokio::test]
     async fn http_transport_detects_version_conflict() {
@@ -6,6 +6,10 @@ use acorn_core::{
 };
 use std::time::{Duration, SystemTime};
 
+mod mock;
+
+pub use mock::{MockController, MockRemote};
+
 #[derive(Debug, Clone)]
 pub struct TrunkContract;
 
@@ -0,0 +1,303 @@
+//! Scriptable in-memory sync remote for deterministic tests of sync and conflict handling.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use acorn_core::BranchId;
+use acorn_sync::{
+    SyncApplyRequest, SyncApplyResponse, SyncBatch, SyncConflict, SyncConflictKind, SyncError, SyncMutation,
+    SyncPullResponse, SyncTransport,
+};
+
+#[derive(Debug, Default)]
+struct MockState {
+    branches: HashMap<BranchId, BTreeMap<String, (Vec<u8>, u64)>>,
+    tombstones: HashMap<BranchId, BTreeMap<String, Option<u64>>>,
+    fail_next: Option<SyncError>,
+    calls: Vec<String>,
+}
+
+/// [`SyncTransport`] backed by an in-memory map. Pair it with the [`MockController`] returned by
+/// [`MockRemote::new`] to simulate remote divergence and transport failures.
+#[derive(Debug, Clone)]
+pub struct MockRemote {
+    state: Arc<Mutex<MockState>>,
+}
+
+/// Handle for scripting a [`MockRemote`] from the test body.
+#[derive(Debug, Clone)]
+pub struct MockController {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockRemote {
+    pub fn new() -> (MockRemote, MockController) {
+        let state = Arc::new(Mutex::new(MockState::default()));
+        (MockRemote { state: state.clone() }, MockController { state })
+    }
+
+    /// Record the call and hand back the injected failure, if one is pending.
+    fn begin(&self, call: String) -> Result<MutexGuard<'_, MockState>, SyncError> {
+        let mut state = lock(&self.state);
+        state.calls.push(call);
+        match state.fail_next.take() {
+            Some(err) => Err(err),
+            None => Ok(state),
+        }
+    }
+}
+
+impl MockController {
+    /// Make the remote hold `value` at `version` for `key`, as if another node had written it.
+    pub fn inject_remote_change(&self, branch: &BranchId, key: &str, value: Vec<u8>, version: u64) {
+        let mut state = lock(&self.state);
+        state
+            .branches
+            .entry(branch.clone())
+            .or_default()
+            .insert(key.to_string(), (value, version));
+        if let Some(tombstones) = state.tombstones.get_mut(branch) {
+            tombstones.remove(key);
+        }
+    }
+
+    /// Delete `key` on the remote, leaving a tombstone for the next `pull`, as if another node had
+    /// removed it.
+    pub fn inject_remote_delete(&self, branch: &BranchId, key: &str) {
+        let mut state = lock(&self.state);
+        let removed = state
+            .branches
+            .get_mut(branch)
+            .and_then(|entries| entries.remove(key));
+        state
+            .tombstones
+            .entry(branch.clone())
+            .or_default()
+            .insert(key.to_string(), removed.map(|(_, version)| version));
+    }
+
+    /// Fail the next `apply` or `pull` with `err`.
+    pub fn fail_next_op(&self, err: SyncError) {
+        lock(&self.state).fail_next = Some(err);
+    }
+
+    /// Calls made against the remote so far, e.g. `pull main` or `apply main (2 ops)`.
+    pub fn record_calls(&self) -> Vec<String> {
+        lock(&self.state).calls.clone()
+    }
+}
+
+impl SyncTransport for MockRemote {
+    fn apply(&self, request: &SyncApplyRequest) -> Result<SyncApplyResponse, SyncError> {
+        let batch = &request.batch;
+        let mut state = self.begin(format!("apply {} ({} ops)", batch.branch, batch.operations.len()))?;
+        let MockState {
+            branches, tombstones, ..
+        } = &mut *state;
+        let entries = branches.entry(batch.branch.clone()).or_default();
+        let tombstones = tombstones.entry(batch.branch.clone()).or_default();
+
+        // Mirrors the server's `apply_batch` over its memory trunk: an expected version only conflicts
+        // with an existing entry, deleting a missing key is reported rather than applied, and deletes
+        // leave tombstones for `pull`.
+        let mut applied = 0usize;
+        let mut conflicts = Vec::new();
+        for op in &batch.operations {
+            let (key, version, local_value) = match op {
+                SyncMutation::Put { key, value, version } => (key, *version, Some(value.clone())),
+                SyncMutation::Delete { key, version } => (key, *version, None),
+            };
+            let current = entries.get(key).map(|(_, v)| *v);
+            if let (Some(expected), Some(existing)) = (version, current) {
+                if expected != existing {
+                    conflicts.push(SyncConflict {
+                        key: key.clone(),
+                        remote_value: entries.get(key).map(|(value, _)| value.clone()),
+                        local_value,
+                        remote_version: current,
+                        local_version: version,
+                        kind: SyncConflictKind::VersionMismatch,
+                    });
+                    continue;
+                }
+            }
+            match op {
+                SyncMutation::Put { value, .. } => {
+                    // A re-created key continues from its tombstone version.
+                    let last = current.or_else(|| tombstones.remove(key).flatten());
+                    entries.insert(key.clone(), (value.clone(), last.unwrap_or(0) + 1));
+                }
+                SyncMutation::Delete { .. } => {
+                    let Some((_, removed)) = entries.remove(key) else {
+                        conflicts.push(SyncConflict {
+                            key: key.clone(),
+                            remote_value: None,
+                            local_value: None,
+                            remote_version: current,
+                            local_version: version,
+                            kind: SyncConflictKind::MissingKey,
+                        });
+                        continue;
+                    };
+                    tombstones.insert(key.clone(), Some(removed));
+                }
+            }
+            applied += 1;
+        }
+        Ok(SyncApplyResponse { applied, conflicts })
+    }
+
+    fn pull(&self, branch: &BranchId) -> Result<SyncPullResponse, SyncError> {
+        let state = self.begin(format!("pull {branch}"))?;
+        let entries = state.branches.get(branch).cloned().unwrap_or_default();
+        let tombstones = state.tombstones.get(branch).cloned().unwrap_or_default();
+        Ok(SyncPullResponse {
+            batch: SyncBatch {
+                branch: branch.clone(),
+                operations: entries
+                    .iter()
+                    .map(|(key, (value, version))| SyncMutation::Put {
+                        key: key.clone(),
+                        value: value.clone(),
+                        version: Some(*version),
+                    })
+                    .collect(),
+            },
+            versions: entries
+                .iter()
+                .map(|(key, (_, version))| (key.clone(), *version))
+                .collect(),
+            deleted: tombstones.keys().cloned().collect(),
+            deleted_versions: tombstones.into_iter().collect(),
+        })
+    }
+}
+
+fn lock(state: &Mutex<MockState>) -> MutexGuard<'_, MockState> {
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acorn_core::{Nut, Tree};
+    use acorn_sync::SyncClient;
+    use acorn_trunk_mem::MemoryTrunk;
+
+    fn nut(value: &[u8]) -> Nut<Vec<u8>> {
+        Nut {
+            value: value.to_vec(),
+        }
+    }
+
+    #[test]
+    fn apply_follows_server_conflict_rules() {
+        let (remote, controller) = MockRemote::new();
+        let branch = BranchId::new("main");
+        controller.inject_remote_change(&branch, "stale", b"remote".to_vec(), 3);
+        controller.inject_remote_change(&branch, "current", b"remote".to_vec(), 2);
+
+        let response = remote
+            .apply(&SyncApplyRequest {
+                batch: SyncBatch {
+                    branch: branch.clone(),
+                    operations: vec![
+                        SyncMutation::Put {
+                            key: "fresh".into(),
+                            value: b"new".to_vec(),
+                            version: Some(7),
+                        },
+                        SyncMutation::Delete {
+                            key: "missing".into(),
+                            version: None,
+                        },
+                        SyncMutation::Delete {
+                            key: "stale".into(),
+                            version: Some(1),
+                        },
+                        SyncMutation::Delete {
+                            key: "current".into(),
+                            version: Some(2),
+                        },
+                    ],
+                },
+            })
+            .unwrap();
+
+        assert_eq!(response.applied, 2);
+        assert_eq!(response.conflicts.len(), 2);
+        assert_eq!(response.conflicts[0].key, "missing");
+        assert!(matches!(response.conflicts[0].kind, SyncConflictKind::MissingKey));
+        assert_eq!(response.conflicts[1].key, "stale");
+        assert_eq!(response.conflicts[1].remote_version, Some(3));
+        assert!(matches!(
+            response.conflicts[1].kind,
+            SyncConflictKind::VersionMismatch
+        ));
+
+        let pulled = remote.pull(&branch).unwrap();
+        assert_eq!(
+            pulled.versions,
+            vec![("fresh".to_string(), 1), ("stale".to_string(), 3)]
+        );
+        assert_eq!(pulled.deleted, vec!["current".to_string()]);
+        assert_eq!(pulled.deleted_versions, vec![("current".to_string(), Some(2))]);
+    }
+
+    #[test]
+    fn push_reports_only_diverged_keys() {
+        let (remote, controller) = MockRemote::new();
+        let branch = BranchId::new("main");
+        let tree = Tree::new(branch.clone(), MemoryTrunk::new());
+        tree.put("a", nut(b"local-a")).unwrap();
+        tree.put("b", nut(b"local-b")).unwrap();
+        controller.inject_remote_change(&branch, "a", b"remote-a".to_vec(), 3);
+
+        let result = SyncClient.push_with_transport(&remote, &tree, &branch).unwrap();
+
+        assert_eq!(result.applied, 1);
+        assert_eq!(result.conflicts, 1);
+        assert_eq!(result.conflict_keys, vec!["a".to_string()]);
+        assert_eq!(controller.record_calls(), vec!["apply main (2 ops)".to_string()]);
+    }
+
+    #[test]
+    fn pull_applies_remote_values_and_tombstones() {
+        let (remote, controller) = MockRemote::new();
+        let branch = BranchId::new("main");
+        let tree = Tree::new(branch.clone(), MemoryTrunk::new());
+        tree.put("doc", nut(b"local")).unwrap();
+        tree.put("gone", nut(b"local")).unwrap();
+        let remote_doc = serde_json::to_vec(&b"remote".to_vec()).unwrap();
+        controller.inject_remote_change(&branch, "doc", remote_doc, 5);
+        controller.inject_remote_change(&branch, "gone", b"[]".to_vec(), 2);
+        controller.inject_remote_delete(&branch, "gone");
+        controller.inject_remote_delete(&branch, "never-local");
+
+        let result = SyncClient
+            .pull_into_with_transport(&remote, &tree, &branch)
+            .unwrap();
+
+        assert_eq!(result.applied, 1);
+        assert_eq!(tree.get("doc").unwrap().unwrap().value, b"remote".to_vec());
+        assert!(tree.get("gone").unwrap().is_none());
+        assert_eq!(controller.record_calls(), vec!["pull main".to_string()]);
+    }
+
+    #[test]
+    fn injected_failure_hits_only_the_next_call() {
+        let (remote, controller) = MockRemote::new();
+        let branch = BranchId::new("main");
+        let tree = Tree::new(branch.clone(), MemoryTrunk::new());
+        tree.put("doc", nut(b"local")).unwrap();
+        controller.fail_next_op(SyncError::Network("partitioned".into()));
+
+        assert!(SyncClient.push_with_transport(&remote, &tree, &branch).is_err());
+        let pulled = SyncClient.pull_with_transport(&remote, &branch).unwrap();
+        assert!(pulled.batch.operations.is_empty());
+        assert_eq!(
+            controller.record_calls(),
+            vec!["apply main (1 ops)".to_string(), "pull main".to_string()]
+        );
+    }
+}
@@ -2,13 +2,13 @@
 
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::SystemTime;
 
 use acorn_core::{
-    AcornError, AcornResult, BranchId, CapabilityAdvertiser, HistoryEvent, HistoryProvider, KeyedTrunk, Nut,
-    TombstoneProvider, Trunk, TrunkCapability, Ttl, TtlCleaner, TtlProvider,
+    AcornError, AcornResult, BranchId, CapabilityAdvertiser, HistoryEvent, HistoryProvider, IoResultExt,
+    KeyedTrunk, Nut, TombstoneProvider, Trunk, TrunkCapability, Ttl, TtlCleaner, TtlProvider,
 };
 use parking_lot::RwLock;
 
@@ -63,7 +63,7 @@ impl FileTrunk {
     }
 
     pub fn init_filesystem(&self) -> AcornResult<()> {
-        fs::create_dir_all(&self.root).map_err(|e| AcornError::Trunk(e.to_string()))
+        fs::create_dir_all(&self.root).with_operation("init_filesystem")
     }
 
     fn branch_dir(&self, branch: &BranchId) -> PathBuf {
@@ -83,14 +83,14 @@ impl FileTrunk {
             return Ok(0);
         }
 
-        fs::create_dir_all(self.versions_dir(branch)).map_err(|e| AcornError::Trunk(e.to_string()))?;
+        fs::create_dir_all(self.versions_dir(branch)).with_operation("bump_version")?;
         let path = self.version_path(branch, key);
         let next = fs::read_to_string(&path)
             .ok()
             .and_then(|raw| raw.parse::<u64>().ok())
             .unwrap_or(0)
             .saturating_add(1);
-        fs::write(&path, next.to_string()).map_err(|e| AcornError::Trunk(e.to_string()))?;
+        fs::write(&path, next.to_string()).with_operation("bump_version")?;
         Ok(next)
     }
 
@@ -143,15 +143,15 @@ impl Trunk<Vec<u8>> for FileTrunk {
         match fs::read(&path) {
             Ok(bytes) => Ok(Some(Nut { value: bytes })),
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
-            Err(e) => Err(AcornError::Trunk(e.to_string())),
+            Err(e) => Err(AcornError::io("get", e)),
         }
     }
 
     fn put(&self, branch: &BranchId, key: &str, nut: Nut<Vec<u8>>) -> AcornResult<()> {
         let dir = self.branch_dir(branch);
-        fs::create_dir_all(&dir).map_err(|e| AcornError::Trunk(e.to_string()))?;
+        fs::create_dir_all(&dir).with_operation("put")?;
         let path = dir.join(key);
-        fs::write(&path, nut.value.clone()).map_err(|e| AcornError::Trunk(e.to_string()))?;
+        fs::write(&path, nut.value.clone()).with_operation("put")?;
         let _ = self.bump_version(branch, key)?;
         self.tombstones.write().remove(&(branch.clone(), key.to_string()));
         if self.history_enabled {
@@ -174,7 +174,7 @@ impl Trunk<Vec<u8>> for FileTrunk {
             if e.kind() == std::io::ErrorKind::NotFound {
                 AcornError::MissingKey(key.to_string())
             } else {
-                AcornError::Trunk(e.to_string())
+                AcornError::io("delete", e)
             }
         })?;
         let version = self.current_version(branch, key);
@@ -223,6 +223,18 @@ impl Trunk<Vec<u8>> for FileTrunk {
         }
         self.delete(branch, key)
     }
+
+    fn flush(&self, branch: &BranchId) -> AcornResult<()> {
+        let dir = self.branch_dir(branch);
+        if !dir.exists() {
+            return Ok(());
+        }
+        sync_dir(&dir).with_operation("flush")?;
+        // The branch directory's own entry lives in the root; sync it so a new branch survives a crash.
+        fs::File::open(&self.root)
+            .and_then(|root| root.sync_all())
+            .with_operation("flush")
+    }
 }
 
 impl KeyedTrunk<Vec<u8>> for FileTrunk {
@@ -309,10 +321,10 @@ impl TtlProvider<Vec<u8>> for FileTrunk {
         }
 
         let dir = self.branch_dir(branch);
-        fs::create_dir_all(&dir).map_err(|e| AcornError::Trunk(e.to_string()))?;
+        fs::create_dir_all(&dir).with_operation("put_with_ttl")?;
         let path = dir.join(key);
 
-        fs::write(&path, nut.value.clone()).map_err(|e| AcornError::Trunk(e.to_string()))?;
+        fs::write(&path, nut.value.clone()).with_operation("put_with_ttl")?;
         let expires_at = ttl
             .expires_at
             .duration_since(SystemTime::UNIX_EPOCH)
@@ -321,7 +333,7 @@ impl TtlProvider<Vec<u8>> for FileTrunk {
             dir.join(format!("{}.ttl", key)),
             expires_at.as_millis().to_string(),
         )
-        .map_err(|e| AcornError::Trunk(e.to_string()))?;
+        .with_operation("put_with_ttl")?;
 
         let _ = self.bump_version(branch, key)?;
         self.tombstones.write().remove(&(branch.clone(), key.to_string()));
@@ -338,6 +350,19 @@ impl TtlProvider<Vec<u8>> for FileTrunk {
     }
 }
 
+/// fsync every file under `dir`, then the directory entries themselves.
+fn sync_dir(dir: &Path) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            sync_dir(&path)?;
+        } else {
+            fs::File::open(&path)?.sync_all()?;
+        }
+    }
+    fs::File::open(dir)?.sync_all()
+}
+
 impl FileTrunk {
     fn history_dir(&self, branch: &BranchId) -> PathBuf {
         self.branch_dir(branch).join(".history")
@@ -345,16 +370,16 @@ impl FileTrunk {
 
     fn append_history(&self, branch: &BranchId, event: HistoryEvent<Vec<u8>>) -> AcornResult<()> {
         let dir = self.history_dir(branch);
-        fs::create_dir_all(&dir).map_err(|e| AcornError::Trunk(e.to_string()))?;
+        fs::create_dir_all(&dir).with_operation("append_history")?;
         let path = dir.join("events.log");
         let mut file = fs::OpenOptions::new()
             .create(true)
             .append(true)
             .open(&path)
-            .map_err(|e| AcornError::Trunk(e.to_string()))?;
-        let line = serde_json::to_string(&event).map_err(|e| AcornError::Trunk(e.to_string()))?;
+            .with_operation("append_history")?;
+        let line = serde_json::to_string(&event)?;
         use std::io::Write;
-        writeln!(file, "{}", line).map_err(|e| AcornError::Trunk(e.to_string()))
+        writeln!(file, "{}", line).with_operation("append_history")
     }
 
     pub fn keys(&self, branch: &BranchId) -> Vec<String> {
@@ -383,26 +408,38 @@ impl FileTrunk {
 
 impl HistoryProvider<Vec<u8>> for FileTrunk {
     fn history(&self, branch: &BranchId) -> AcornResult<Vec<HistoryEvent<Vec<u8>>>> {
+        Ok(self.history_since(branch, 0)?.0)
+    }
+
+    /// Only the lines after `start` are decoded; earlier ones are just counted.
+    fn history_since(
+        &self,
+        branch: &BranchId,
+        start: usize,
+    ) -> AcornResult<(Vec<HistoryEvent<Vec<u8>>>, usize)> {
         if !self.history_enabled {
-            return Ok(Vec::new());
+            return Ok((Vec::new(), 0));
         }
 
         let path = self.history_dir(branch).join("events.log");
         if !path.exists() {
-            return Ok(Vec::new());
+            return Ok((Vec::new(), 0));
         }
 
-        let data = fs::read_to_string(&path).map_err(|e| AcornError::Trunk(e.to_string()))?;
+        let data = fs::read_to_string(&path).with_operation("history")?;
         let mut events = Vec::new();
+        let mut total = 0;
         for line in data.lines() {
             if line.trim().is_empty() {
                 continue;
             }
-            let event: HistoryEvent<Vec<u8>> =
-                serde_json::from_str(line).map_err(|e| AcornError::Trunk(e.to_string()))?;
-            events.push(event);
+            if total >= start {
+                let event: HistoryEvent<Vec<u8>> = serde_json::from_str(line)?;
+                events.push(event);
+            }
+            total += 1;
         }
-        Ok(events)
+        Ok((events, total))
     }
 }
 #[cfg(test)]
@@ -439,6 +476,29 @@ mod tests {
         assert!(!fs::metadata(tmp_dir.path().join("main").join("key")).is_ok());
     }
 
+    #[test]
+    fn flushed_writes_survive_reopen() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let branch = BranchId::new("main");
+        let tree = acorn_core::Tree::new(branch.clone(), FileTrunk::with_history(tmp_dir.path()));
+        tree.put(
+            "key",
+            Nut {
+                value: b"durable".to_vec(),
+            },
+        )
+        .unwrap();
+        tree.flush().unwrap();
+        drop(tree);
+
+        let reopened = FileTrunk::with_history(tmp_dir.path());
+        assert_eq!(
+            reopened.get(&branch, "key").unwrap().unwrap().value,
+            b"durable".to_vec()
+        );
+        assert_eq!(reopened.version(&branch, "key"), Some(1));
+    }
+
     #[test]
     fn respects_ttl_when_enabled() {
         let tmp_dir = tempfile::tempdir().unwrap();
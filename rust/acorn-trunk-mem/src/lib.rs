@@ -15,15 +15,65 @@ pub struct MemoryTrunk {
     inner: Arc<RwLock<Inner>>,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 struct Inner {
     data: HashMap<(BranchId, String), Vec<u8>>,
     history: HashMap<BranchId, Vec<HistoryEvent<Vec<u8>>>>,
     ttl: HashMap<(BranchId, String), SystemTime>,
     versions: HashMap<(BranchId, String), u64>,
     tombstones: HashMap<(BranchId, String), Option<u64>>,
+    /// Highest version each key held before a [`MemoryTrunk::restore`] rolled it back.
+    retired: HashMap<(BranchId, String), u64>,
 }
 
+impl Inner {
+    /// Versions continue past the live version, the tombstone and any version rolled back by a
+    /// restore, so a key never reuses a version.
+    fn next_version(&self, branch: &BranchId, key: &str) -> u64 {
+        let id = (branch.clone(), key.to_string());
+        [
+            self.versions.get(&id).copied(),
+            self.tombstones.get(&id).copied().flatten(),
+            self.retired.get(&id).copied(),
+        ]
+        .into_iter()
+        .flatten()
+        .max()
+        .unwrap_or(0)
+        .saturating_add(1)
+    }
+
+    fn is_expired(&self, id: &(BranchId, String)) -> bool {
+        self.ttl
+            .get(id)
+            .is_some_and(|expires_at| SystemTime::now() >= *expires_at)
+    }
+
+    /// Remove `key` if its TTL has passed, recorded like any other delete. Returns whether it was removed.
+    fn purge_if_expired(&mut self, branch: &BranchId, key: &str) -> bool {
+        let id = (branch.clone(), key.to_string());
+        if !self.is_expired(&id) {
+            return false;
+        }
+        self.ttl.remove(&id);
+        self.data.remove(&id);
+        let removed_version = self.versions.remove(&id);
+        self.tombstones.insert(id, removed_version);
+        self.history
+            .entry(branch.clone())
+            .or_default()
+            .push(HistoryEvent::Delete { key: key.to_string() });
+        true
+    }
+}
+
+const CAPABILITIES: &[TrunkCapability] = &[
+    TrunkCapability::History,
+    TrunkCapability::Ttl,
+    TrunkCapability::Versions,
+    TrunkCapability::MonotonicVersions,
+];
+
 impl MemoryTrunk {
     pub fn new() -> Self {
         Self::default()
@@ -43,30 +93,60 @@ impl MemoryTrunk {
             .collect()
     }
 
+    /// Version of the live value at `key`; `None` once its TTL has passed, even before it is purged.
     pub fn current_version(&self, branch: &BranchId, key: &str) -> Option<u64> {
         let guard = self.inner.read();
-        guard.versions.get(&(branch.clone(), key.to_string())).copied()
+        let id = (branch.clone(), key.to_string());
+        if guard.is_expired(&id) {
+            return None;
+        }
+        guard.versions.get(&id).copied()
+    }
+
+    /// Capture the full trunk state (all branches, versions, history, TTLs) for a later [`restore`].
+    ///
+    /// [`restore`]: MemoryTrunk::restore
+    pub fn snapshot(&self) -> MemorySnapshot {
+        MemorySnapshot {
+            inner: self.inner.read().clone(),
+        }
+    }
+
+    /// Reset the trunk's data, history and TTLs to the state captured by `snapshot`. Versions handed
+    /// out since the snapshot are remembered, so later writes still get versions never seen before.
+    pub fn restore(&self, snapshot: &MemorySnapshot) {
+        let mut guard = self.inner.write();
+        let mut restored = snapshot.inner.clone();
+        let issued = guard
+            .versions
+            .iter()
+            .map(|(id, version)| (id, *version))
+            .chain(
+                guard
+                    .tombstones
+                    .iter()
+                    .filter_map(|(id, version)| Some((id, (*version)?))),
+            )
+            .chain(guard.retired.iter().map(|(id, version)| (id, *version)));
+        for (id, version) in issued {
+            let floor = restored.retired.entry(id.clone()).or_default();
+            *floor = (*floor).max(version);
+        }
+        *guard = restored;
     }
 }
 
+/// Opaque point-in-time copy of a [`MemoryTrunk`], useful as a test fixture baseline.
+#[derive(Debug, Clone)]
+pub struct MemorySnapshot {
+    inner: Inner,
+}
+
 impl Trunk<Vec<u8>> for MemoryTrunk {
     fn get(&self, branch: &BranchId, key: &str) -> AcornResult<Option<Nut<Vec<u8>>>> {
         let mut guard = self.inner.write();
-        if let Some(expires_at) = guard.ttl.get(&(branch.clone(), key.to_string())) {
-            if SystemTime::now() >= *expires_at {
-                guard.ttl.remove(&(branch.clone(), key.to_string()));
-                guard.data.remove(&(branch.clone(), key.to_string()));
-                let removed_version = guard.versions.remove(&(branch.clone(), key.to_string()));
-                guard
-                    .tombstones
-                    .insert((branch.clone(), key.to_string()), removed_version);
-                guard
-                    .history
-                    .entry(branch.clone())
-                    .or_default()
-                    .push(HistoryEvent::Delete { key: key.to_string() });
-                return Ok(None);
-            }
+        if guard.purge_if_expired(branch, key) {
+            return Ok(None);
         }
 
         Ok(guard
@@ -78,12 +158,8 @@ impl Trunk<Vec<u8>> for MemoryTrunk {
 
     fn put(&self, branch: &BranchId, key: &str, nut: Nut<Vec<u8>>) -> AcornResult<()> {
         let mut guard = self.inner.write();
-        let next_version = guard
-            .versions
-            .get(&(branch.clone(), key.to_string()))
-            .copied()
-            .unwrap_or(0)
-            .saturating_add(1);
+        guard.purge_if_expired(branch, key);
+        let next_version = guard.next_version(branch, key);
         guard
             .versions
             .insert((branch.clone(), key.to_string()), next_version);
@@ -134,6 +210,7 @@ impl Trunk<Vec<u8>> for MemoryTrunk {
         nut: Nut<Vec<u8>>,
     ) -> AcornResult<()> {
         let mut guard = self.inner.write();
+        guard.purge_if_expired(branch, key);
         let current = guard.versions.get(&(branch.clone(), key.to_string())).copied();
         if let Some(expected) = expected {
             if current != Some(expected) {
@@ -143,7 +220,35 @@ impl Trunk<Vec<u8>> for MemoryTrunk {
                 });
             }
         }
-        let next_version = current.unwrap_or(0).saturating_add(1);
+        let next_version = guard.next_version(branch, key);
+        guard
+            .versions
+            .insert((branch.clone(), key.to_string()), next_version);
+        guard
+            .history
+            .entry(branch.clone())
+            .or_default()
+            .push(HistoryEvent::Put {
+                key: key.to_string(),
+                nut: Nut {
+                    value: nut.value.clone(),
+                },
+            });
+        guard.tombstones.remove(&(branch.clone(), key.to_string()));
+        guard.data.insert((branch.clone(), key.to_string()), nut.value);
+        Ok(())
+    }
+
+    fn put_if_absent(&self, branch: &BranchId, key: &str, nut: Nut<Vec<u8>>) -> AcornResult<()> {
+        let mut guard = self.inner.write();
+        guard.purge_if_expired(branch, key);
+        if guard.data.contains_key(&(branch.clone(), key.to_string())) {
+            return Err(AcornError::VersionConflict {
+                expected: None,
+                actual: guard.versions.get(&(branch.clone(), key.to_string())).copied(),
+            });
+        }
+        let next_version = guard.next_version(branch, key);
         guard
             .versions
             .insert((branch.clone(), key.to_string()), next_version);
@@ -164,6 +269,7 @@ impl Trunk<Vec<u8>> for MemoryTrunk {
 
     fn delete_if_version(&self, branch: &BranchId, key: &str, expected: Option<u64>) -> AcornResult<()> {
         let mut guard = self.inner.write();
+        guard.purge_if_expired(branch, key);
         let current = guard.versions.get(&(branch.clone(), key.to_string())).copied();
         if let Some(expected) = expected {
             if current != Some(expected) {
@@ -192,6 +298,10 @@ impl Trunk<Vec<u8>> for MemoryTrunk {
 
         Ok(())
     }
+
+    fn capabilities(&self) -> &'static [TrunkCapability] {
+        CAPABILITIES
+    }
 }
 
 impl KeyedTrunk<Vec<u8>> for MemoryTrunk {
@@ -243,23 +353,14 @@ impl TtlCleaner<Vec<u8>> for MemoryTrunk {
 
 impl CapabilityAdvertiser for MemoryTrunk {
     fn capabilities(&self) -> &'static [TrunkCapability] {
-        &[
-            TrunkCapability::History,
-            TrunkCapability::Ttl,
-            TrunkCapability::Versions,
-        ]
+        CAPABILITIES
     }
 }
 
 impl TtlProvider<Vec<u8>> for MemoryTrunk {
     fn put_with_ttl(&self, branch: &BranchId, key: &str, nut: Nut<Vec<u8>>, ttl: Ttl) -> AcornResult<()> {
         let mut guard = self.inner.write();
-        let next_version = guard
-            .versions
-            .get(&(branch.clone(), key.to_string()))
-            .copied()
-            .unwrap_or(0)
-            .saturating_add(1);
+        let next_version = guard.next_version(branch, key);
         guard
             .versions
             .insert((branch.clone(), key.to_string()), next_version);
@@ -286,12 +387,26 @@ impl HistoryProvider<Vec<u8>> for MemoryTrunk {
         let guard = self.inner.read();
         Ok(guard.history.get(branch).cloned().unwrap_or_else(Vec::new))
     }
+
+    fn history_since(
+        &self,
+        branch: &BranchId,
+        start: usize,
+    ) -> AcornResult<(Vec<HistoryEvent<Vec<u8>>>, usize)> {
+        let guard = self.inner.read();
+        let events = guard.history.get(branch).map(Vec::as_slice).unwrap_or_default();
+        Ok((events.get(start..).unwrap_or_default().to_vec(), events.len()))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use acorn_core::{CapabilityAdvertiser, EncodedTree, JsonCodec, Tree};
+    use acorn_core::json::PatchOp;
+    use acorn_core::{
+        CachedTree, CapabilityAdvertiser, ChangeCounts, ChangeType, CopyOptions, CopyReport, EncodedTree,
+        JsonCodec, Progress, Tree,
+    };
     #[cfg(feature = "contract-tests")]
     use acorn_test_harness::TrunkContract;
     #[cfg(feature = "contract-tests")]
@@ -376,6 +491,69 @@ mod tests {
         assert!(trunk.get(&branch, "key").unwrap().is_none());
     }
 
+    #[test]
+    fn tree_put_expiring_in_is_purged() {
+        let trunk = MemoryTrunk::new();
+        let branch = BranchId::new("ttl-tree");
+        let tree = Tree::new(branch.clone(), trunk.clone());
+
+        tree.put_expiring_in(
+            "key",
+            Nut {
+                value: b"short".to_vec(),
+            },
+            std::time::Duration::from_millis(0),
+        )
+        .unwrap();
+
+        assert_eq!(trunk.purge_expired(&branch), 1);
+        assert!(tree.get("key").unwrap().is_none());
+    }
+
+    #[test]
+    fn restore_discards_changes_since_snapshot() {
+        let trunk = MemoryTrunk::new();
+        let tree = Tree::new(BranchId::new("fixture"), trunk.clone());
+        tree.put("keep", Nut { value: b"1".to_vec() }).unwrap();
+        let baseline = trunk.snapshot();
+
+        tree.put("keep", Nut { value: b"2".to_vec() }).unwrap();
+        tree.put("extra", Nut { value: b"x".to_vec() }).unwrap();
+        trunk.restore(&baseline);
+
+        assert_eq!(tree.get("keep").unwrap().unwrap().value, b"1".to_vec());
+        assert!(tree.get("extra").unwrap().is_none());
+        assert_eq!(trunk.current_version(tree.branch(), "keep"), Some(1));
+    }
+
+    #[test]
+    fn ttl_sweeper_reclaims_expired_entries() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::{Duration, Instant};
+
+        let trunk = MemoryTrunk::new();
+        let tree = Tree::new(BranchId::new("sweep"), trunk.clone());
+        tree.put_expiring_in("key", Nut { value: b"x".to_vec() }, Duration::from_millis(10))
+            .unwrap();
+
+        let reclaimed = Arc::new(AtomicUsize::new(0));
+        let sweeper = {
+            let reclaimed = reclaimed.clone();
+            tree.spawn_ttl_sweeper(Duration::from_millis(5), move |removed| {
+                reclaimed.fetch_add(removed, Ordering::SeqCst);
+            })
+        };
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while reclaimed.load(Ordering::SeqCst) == 0 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        drop(sweeper);
+
+        assert_eq!(reclaimed.load(Ordering::SeqCst), 1);
+        assert!(trunk.keys(tree.branch()).is_empty());
+    }
+
     #[test]
     fn reports_capabilities() {
         let trunk = MemoryTrunk::new();
@@ -402,6 +580,369 @@ mod tests {
         assert_eq!(fetched.value, value);
     }
 
+    #[test]
+    fn concurrent_increments_are_not_lost() {
+        let tree = Arc::new(EncodedTree::<i64, _, _>::new(
+            BranchId::new("counter"),
+            MemoryTrunk::new(),
+            JsonCodec,
+        ));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let tree = tree.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..50 {
+                        tree.increment("hits", 1).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(tree.get("hits").unwrap().unwrap().value, 400);
+        assert_eq!(tree.increment("hits", -400).unwrap(), 0);
+    }
+
+    #[test]
+    fn concurrent_upserts_append_without_losing_updates() {
+        let tree = Arc::new(EncodedTree::<Vec<u32>, _, _>::new(
+            BranchId::new("upsert"),
+            MemoryTrunk::new(),
+            JsonCodec,
+        ));
+        let handles: Vec<_> = (0..8u32)
+            .map(|worker| {
+                let tree = tree.clone();
+                std::thread::spawn(move || {
+                    for i in 0..25 {
+                        tree.upsert_with("log", |current| {
+                            let mut entries = current.unwrap_or_default();
+                            entries.push(worker * 100 + i);
+                            entries
+                        })
+                        .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let mut entries = tree.get("log").unwrap().unwrap().value;
+        entries.sort_unstable();
+        let expected: Vec<u32> = (0..8).flat_map(|w| (0..25).map(move |i| w * 100 + i)).collect();
+        assert_eq!(entries, expected);
+    }
+
+    #[test]
+    fn upsert_gives_up_under_constant_contention() {
+        #[derive(Debug, Clone, Default)]
+        struct AlwaysRacing(MemoryTrunk);
+
+        impl Trunk<Vec<u8>> for AlwaysRacing {
+            fn get(&self, branch: &BranchId, key: &str) -> AcornResult<Option<Nut<Vec<u8>>>> {
+                self.0.get(branch, key)
+            }
+            fn put(&self, branch: &BranchId, key: &str, nut: Nut<Vec<u8>>) -> AcornResult<()> {
+                self.0.put(branch, key, nut)
+            }
+            fn delete(&self, branch: &BranchId, key: &str) -> AcornResult<()> {
+                self.0.delete(branch, key)
+            }
+            fn version(&self, _branch: &BranchId, _key: &str) -> Option<u64> {
+                Some(1)
+            }
+            fn put_if_version(
+                &self,
+                _branch: &BranchId,
+                _key: &str,
+                expected: Option<u64>,
+                _nut: Nut<Vec<u8>>,
+            ) -> AcornResult<()> {
+                Err(AcornError::VersionConflict {
+                    expected,
+                    actual: Some(2),
+                })
+            }
+        }
+
+        let tree = EncodedTree::<i64, _, _>::new(BranchId::new("racing"), AlwaysRacing::default(), JsonCodec);
+        let mut calls = 0;
+        let result = tree.upsert_with("hits", |current| {
+            calls += 1;
+            current.unwrap_or(0) + 1
+        });
+
+        assert!(matches!(result, Err(AcornError::VersionConflict { .. })));
+        assert_eq!(calls, acorn_core::MAX_UPDATE_ATTEMPTS);
+    }
+
+    #[test]
+    fn merge_updates_fields_and_creates_missing_documents() {
+        let tree = EncodedTree::new(BranchId::new("merge"), MemoryTrunk::new(), JsonCodec);
+        tree.put(
+            "person",
+            Nut {
+                value: serde_json::json!({"name": "A", "age": 30}),
+            },
+        )
+        .unwrap();
+
+        tree.merge("person", &serde_json::json!({"age": 31})).unwrap();
+        tree.merge("fresh", &serde_json::json!({"name": "B"})).unwrap();
+        assert_eq!(
+            tree.get_field("person", "age").unwrap(),
+            Some(serde_json::json!(31))
+        );
+        assert_eq!(tree.get_field("person", "address.city").unwrap(), None);
+
+        assert_eq!(
+            tree.get("person").unwrap().unwrap().value,
+            serde_json::json!({"name": "A", "age": 31})
+        );
+        assert_eq!(
+            tree.get("fresh").unwrap().unwrap().value,
+            serde_json::json!({"name": "B"})
+        );
+    }
+
+    #[test]
+    fn rejected_json_patch_is_not_written() {
+        let tree = EncodedTree::new(BranchId::new("patch"), MemoryTrunk::new(), JsonCodec);
+        tree.apply_merge_patch("doc", &serde_json::json!({"a": 1, "b": 2}))
+            .unwrap();
+        tree.apply_merge_patch("doc", &serde_json::json!({"b": null}))
+            .unwrap();
+
+        let ops = [
+            PatchOp::Add {
+                path: "/c".into(),
+                value: serde_json::json!(3),
+            },
+            PatchOp::Test {
+                path: "/a".into(),
+                value: serde_json::json!(0),
+            },
+        ];
+        assert!(tree.apply_json_patch("doc", &ops).is_err());
+        assert_eq!(
+            tree.get("doc").unwrap().unwrap().value,
+            serde_json::json!({"a": 1})
+        );
+    }
+
+    #[test]
+    fn values_stream_decodes_prefix_in_key_order() {
+        let tree = EncodedTree::new(BranchId::new("stream"), MemoryTrunk::new(), JsonCodec);
+        for (key, msg) in [("user/2", "b"), ("order/1", "x"), ("user/1", "a")] {
+            tree.put(
+                key,
+                Nut {
+                    value: Demo { msg: msg.into() },
+                },
+            )
+            .unwrap();
+        }
+
+        let streamed: Vec<String> = tree
+            .values_stream("user/")
+            .map(|demo| demo.unwrap().msg)
+            .collect();
+        assert_eq!(streamed, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn values_stream_decodes_one_value_per_step() {
+        use acorn_core::NutCodec;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        #[derive(Clone)]
+        struct CountingCodec(Arc<AtomicUsize>);
+
+        impl NutCodec<Demo> for CountingCodec {
+            fn encode(&self, value: &Demo) -> AcornResult<Vec<u8>> {
+                JsonCodec.encode(value)
+            }
+
+            fn decode(&self, bytes: &[u8]) -> AcornResult<Demo> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                JsonCodec.decode(bytes)
+            }
+        }
+
+        let decodes = Arc::new(AtomicUsize::new(0));
+        let tree = EncodedTree::new(
+            BranchId::new("stream-lazy"),
+            MemoryTrunk::new(),
+            CountingCodec(decodes.clone()),
+        );
+        for i in 0..3 {
+            tree.put(
+                &format!("item/{i}"),
+                Nut {
+                    value: Demo { msg: i.to_string() },
+                },
+            )
+            .unwrap();
+        }
+
+        let mut stream = tree.values_stream("item/");
+        assert_eq!(decodes.load(Ordering::SeqCst), 0);
+        assert_eq!(stream.next().unwrap().unwrap().msg, "0");
+        assert_eq!(decodes.load(Ordering::SeqCst), 1);
+
+        // Values are read when reached, so a write ahead of the cursor is what gets decoded.
+        tree.put(
+            "item/1",
+            Nut {
+                value: Demo {
+                    msg: "updated".into(),
+                },
+            },
+        )
+        .unwrap();
+        assert_eq!(stream.next().unwrap().unwrap().msg, "updated");
+        assert_eq!(decodes.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn increment_rejects_non_numeric_value() {
+        let trunk = MemoryTrunk::new();
+        let branch = BranchId::new("counter-bad");
+        trunk
+            .put(
+                &branch,
+                "hits",
+                Nut {
+                    value: b"\"many\"".to_vec(),
+                },
+            )
+            .unwrap();
+
+        let tree = EncodedTree::<i64, _, _>::new(branch, trunk, JsonCodec);
+        assert!(matches!(
+            tree.increment("hits", 1),
+            Err(AcornError::Serialization(_))
+        ));
+    }
+
+    #[test]
+    fn increment_reports_overflow_and_keeps_value() {
+        let tree = EncodedTree::<i64, _, _>::new(BranchId::new("counter-max"), MemoryTrunk::new(), JsonCodec);
+        assert_eq!(tree.increment("hits", i64::MAX).unwrap(), i64::MAX);
+
+        assert!(matches!(
+            tree.increment("hits", 1),
+            Err(AcornError::Overflow { .. })
+        ));
+        assert_eq!(tree.get("hits").unwrap().unwrap().value, i64::MAX);
+    }
+
+    #[test]
+    fn cached_tree_hits_and_sees_external_writes() {
+        let trunk = MemoryTrunk::new();
+        let branch = BranchId::new("cached");
+        let writer = EncodedTree::new(branch.clone(), trunk.clone(), JsonCodec);
+        let cached = CachedTree::new(EncodedTree::new(branch, trunk, JsonCodec), 8);
+
+        writer
+            .put(
+                "key",
+                Nut {
+                    value: Demo { msg: "one".into() },
+                },
+            )
+            .unwrap();
+        assert_eq!(cached.get("key").unwrap().unwrap().msg, "one");
+        assert_eq!(cached.get("key").unwrap().unwrap().msg, "one");
+        assert_eq!((cached.hits(), cached.misses()), (1, 1));
+
+        writer
+            .put(
+                "key",
+                Nut {
+                    value: Demo { msg: "two".into() },
+                },
+            )
+            .unwrap();
+        assert_eq!(cached.get("key").unwrap().unwrap().msg, "two");
+        assert_eq!((cached.hits(), cached.misses()), (1, 2));
+    }
+
+    #[test]
+    fn cached_tree_sees_delete_then_reput_through_other_handle() {
+        let trunk = MemoryTrunk::new();
+        let branch = BranchId::new("cached-reput");
+        let writer = EncodedTree::new(branch.clone(), trunk.clone(), JsonCodec);
+        let cached = CachedTree::new(EncodedTree::new(branch.clone(), trunk.clone(), JsonCodec), 8);
+
+        writer
+            .put(
+                "key",
+                Nut {
+                    value: Demo { msg: "a".into() },
+                },
+            )
+            .unwrap();
+        assert_eq!(cached.get("key").unwrap().unwrap().msg, "a");
+
+        writer.delete("key").unwrap();
+        writer
+            .put(
+                "key",
+                Nut {
+                    value: Demo { msg: "b".into() },
+                },
+            )
+            .unwrap();
+        assert_eq!(trunk.current_version(&branch, "key"), Some(2));
+        assert_eq!(cached.get("key").unwrap().unwrap().msg, "b");
+    }
+
+    #[test]
+    fn cached_tree_does_not_serve_expired_values() {
+        let trunk = MemoryTrunk::new();
+        let branch = BranchId::new("cached-ttl");
+        let cached = CachedTree::new(EncodedTree::new(branch.clone(), trunk.clone(), JsonCodec), 8);
+        let raw = Tree::new(branch.clone(), trunk.clone());
+        let bytes = serde_json::to_vec(&Demo { msg: "short".into() }).unwrap();
+
+        raw.put_expiring_in("key", Nut { value: bytes }, std::time::Duration::from_millis(20))
+            .unwrap();
+        assert_eq!(cached.get("key").unwrap().unwrap().msg, "short");
+        assert_eq!(cached.get("key").unwrap().unwrap().msg, "short");
+        assert_eq!(cached.hits(), 1);
+
+        std::thread::sleep(std::time::Duration::from_millis(40));
+        assert_eq!(trunk.current_version(&branch, "key"), None);
+        assert!(cached.get("key").unwrap().is_none());
+    }
+
+    #[test]
+    fn cached_tree_sees_writes_after_restore() {
+        let trunk = MemoryTrunk::new();
+        let branch = BranchId::new("cached-restore");
+        let writer = EncodedTree::new(branch.clone(), trunk.clone(), JsonCodec);
+        let cached = CachedTree::new(EncodedTree::new(branch.clone(), trunk.clone(), JsonCodec), 8);
+        let demo = |msg: &str| Nut {
+            value: Demo { msg: msg.into() },
+        };
+
+        writer.put("key", demo("baseline")).unwrap();
+        let baseline = trunk.snapshot();
+        writer.put("key", demo("discarded")).unwrap();
+        assert_eq!(cached.get("key").unwrap().unwrap().msg, "discarded");
+
+        trunk.restore(&baseline);
+        writer.put("key", demo("after-restore")).unwrap();
+
+        assert_eq!(trunk.current_version(&branch, "key"), Some(3));
+        assert_eq!(cached.get("key").unwrap().unwrap().msg, "after-restore");
+    }
+
     #[test]
     fn versions_increment_on_put() {
         let trunk = MemoryTrunk::new();
@@ -477,6 +1018,354 @@ mod tests {
         assert!(matches!(res, Err(AcornError::VersionConflict { .. })));
     }
 
+    #[test]
+    fn compare_and_swap_matches_current_value() {
+        let tree = Tree::new(BranchId::new("cas"), MemoryTrunk::new());
+
+        assert!(tree.compare_and_swap("leader", None, b"a".to_vec()).unwrap());
+        assert!(!tree.compare_and_swap("leader", None, b"b".to_vec()).unwrap());
+        assert!(!tree
+            .compare_and_swap("leader", Some(&b"b".to_vec()), b"c".to_vec())
+            .unwrap());
+        assert!(tree
+            .compare_and_swap("leader", Some(&b"a".to_vec()), b"c".to_vec())
+            .unwrap());
+        assert_eq!(tree.get("leader").unwrap().unwrap().value, b"c".to_vec());
+    }
+
+    #[test]
+    fn compare_and_swap_treats_expired_key_as_absent() {
+        let tree = Tree::new(BranchId::new("cas-ttl"), MemoryTrunk::new());
+        tree.put_expiring_in(
+            "lease",
+            Nut {
+                value: b"old".to_vec(),
+            },
+            std::time::Duration::from_millis(0),
+        )
+        .unwrap();
+
+        assert!(tree.compare_and_swap("lease", None, b"new".to_vec()).unwrap());
+        assert_eq!(tree.get("lease").unwrap().unwrap().value, b"new".to_vec());
+    }
+
+    #[test]
+    fn iterates_tree_by_reference() {
+        let tree = Tree::new(BranchId::new("iter"), MemoryTrunk::new());
+        for key in ["b", "a", "c"] {
+            tree.put(
+                key,
+                Nut {
+                    value: key.as_bytes().to_vec(),
+                },
+            )
+            .unwrap();
+        }
+
+        let mut seen = Vec::new();
+        for item in &tree {
+            let (key, nut) = item.unwrap();
+            assert_eq!(nut.value, key.as_bytes().to_vec());
+            seen.push(key);
+        }
+        assert_eq!(seen, vec!["a", "b", "c"]);
+        assert_eq!(seen.len(), tree.trunk().keys(tree.branch()).len());
+    }
+
+    #[test]
+    fn iter_hints_remaining_keys() {
+        let tree = Tree::new(BranchId::new("iter-hint"), MemoryTrunk::new());
+        for key in ["a", "b", "c"] {
+            tree.put(key, Nut { value: vec![0] }).unwrap();
+        }
+
+        let mut iter = tree.iter();
+        assert_eq!((iter.remaining_hint(), iter.size_hint()), (3, (0, Some(3))));
+        iter.next().unwrap().unwrap();
+        assert_eq!((iter.remaining_hint(), iter.size_hint()), (2, (0, Some(2))));
+
+        // A key deleted after the listing is still counted by the hint but never yielded, which is
+        // why the lower bound cannot promise anything.
+        tree.delete("c").unwrap();
+        assert_eq!(iter.size_hint(), (0, Some(2)));
+        assert_eq!(iter.count(), 1);
+    }
+
+    #[test]
+    fn generated_keys_iterate_in_insertion_order() {
+        let tree = Tree::new(BranchId::new("generated"), MemoryTrunk::new());
+        let keys: Vec<String> = (0u8..5)
+            .map(|i| tree.put_generated(Nut { value: vec![i] }).unwrap())
+            .collect();
+
+        let values: Vec<Vec<u8>> = tree.iter().map(|item| item.unwrap().1.value).collect();
+        assert_eq!(values, (0u8..5).map(|i| vec![i]).collect::<Vec<_>>());
+        assert_eq!(tree.get(&keys[2]).unwrap().unwrap().value, vec![2]);
+    }
+
+    #[test]
+    fn polls_keys_changed_since_watermark() {
+        let tree = Tree::new(BranchId::new("poll"), MemoryTrunk::new());
+        tree.put("a", Nut { value: vec![1] }).unwrap();
+        let (_, watermark) = tree.keys_changed_since(0).unwrap();
+
+        tree.put("b", Nut { value: vec![2] }).unwrap();
+        tree.put("c", Nut { value: vec![3] }).unwrap();
+        tree.put("c", Nut { value: vec![4] }).unwrap();
+        tree.delete("a").unwrap();
+
+        let (changes, next) = tree.keys_changed_since(watermark).unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                ("b".to_string(), ChangeType::Put),
+                ("c".to_string(), ChangeType::Put),
+                ("a".to_string(), ChangeType::Delete),
+            ]
+        );
+        assert!(tree.keys_changed_since(next).unwrap().0.is_empty());
+    }
+
+    #[test]
+    fn rejects_watermark_past_restored_history() {
+        let trunk = MemoryTrunk::new();
+        let tree = Tree::new(BranchId::new("poll-restore"), trunk.clone());
+        let baseline = trunk.snapshot();
+        tree.put("a", Nut { value: vec![1] }).unwrap();
+        tree.put("b", Nut { value: vec![2] }).unwrap();
+        let (_, watermark) = tree.keys_changed_since(0).unwrap();
+
+        trunk.restore(&baseline);
+
+        assert!(matches!(
+            tree.keys_changed_since(watermark),
+            Err(AcornError::Configuration {
+                parameter: "watermark",
+                ..
+            })
+        ));
+        assert_eq!(tree.keys_changed_since(0).unwrap(), (Vec::new(), 0));
+    }
+
+    #[test]
+    fn counts_every_change_since_watermark() {
+        let tree = Tree::new(BranchId::new("counts"), MemoryTrunk::new());
+        tree.put("seed", Nut { value: vec![0] }).unwrap();
+        let (_, watermark) = tree.change_counts_since(0).unwrap();
+
+        for key in ["a", "b", "a"] {
+            tree.put(key, Nut { value: vec![1] }).unwrap();
+        }
+        tree.delete("seed").unwrap();
+
+        let (counts, _) = tree.change_counts_since(watermark).unwrap();
+        assert_eq!(counts, ChangeCounts { put: 3, delete: 1 });
+    }
+
+    #[test]
+    fn delete_prefix_leaves_other_namespaces() {
+        let tree = Tree::new(BranchId::new("prefix"), MemoryTrunk::new());
+        for key in ["user-1", "user-2", "order-1"] {
+            tree.put(key, Nut { value: b"x".to_vec() }).unwrap();
+        }
+
+        assert_eq!(tree.count_prefix("user-"), 2);
+        assert_eq!(tree.count_prefix(""), 3);
+        assert_eq!(tree.delete_prefix("user-").unwrap(), 2);
+        assert_eq!(tree.count_prefix("user-"), 0);
+        assert!(tree.get("user-1").unwrap().is_none());
+        assert!(tree.get("order-1").unwrap().is_some());
+
+        assert_eq!(tree.clear().unwrap(), 1);
+        assert!(tree.trunk().keys(tree.branch()).is_empty());
+    }
+
+    #[test]
+    fn export_then_import_into_fresh_tree() {
+        let source = Tree::new(BranchId::new("dump"), MemoryTrunk::new());
+        for i in 0..3 {
+            source
+                .put(
+                    &format!("key-{}", i),
+                    Nut {
+                        value: vec![0, i, 255],
+                    },
+                )
+                .unwrap();
+        }
+
+        let mut dump = Vec::new();
+        assert_eq!(source.export_all(&mut dump).unwrap(), 3);
+        assert_eq!(dump.iter().filter(|b| **b == b'\n').count(), 3);
+
+        let dest = Tree::new(BranchId::new("restore"), MemoryTrunk::new());
+        assert_eq!(dest.import_all(dump.as_slice()).unwrap(), 3);
+        for i in 0..3 {
+            let nut = dest.get(&format!("key-{}", i)).unwrap().unwrap();
+            assert_eq!(nut.value, vec![0, i, 255]);
+        }
+    }
+
+    #[test]
+    fn copy_to_filters_prefix_and_transforms() {
+        let source = Tree::new(BranchId::new("src"), MemoryTrunk::new());
+        for key in ["user-1", "user-2", "order-1"] {
+            source
+                .put(
+                    key,
+                    Nut {
+                        value: key.as_bytes().to_vec(),
+                    },
+                )
+                .unwrap();
+        }
+        let dest = Tree::new(BranchId::new("dst"), MemoryTrunk::new());
+
+        let report = source
+            .copy_to(
+                &dest,
+                CopyOptions {
+                    key_prefix: Some("user-".into()),
+                    transform: Some(Box::new(|key: &str, value: Vec<u8>| {
+                        (key != "user-2").then(|| value.to_ascii_uppercase())
+                    })),
+                    ..CopyOptions::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(
+            report,
+            CopyReport {
+                copied: 1,
+                skipped: 1,
+                transformed: 1
+            }
+        );
+        assert_eq!(dest.get("user-1").unwrap().unwrap().value, b"USER-1".to_vec());
+        assert!(dest.get("user-2").unwrap().is_none());
+        assert!(dest.get("order-1").unwrap().is_none());
+    }
+
+    #[test]
+    fn export_reports_progress_to_completion() {
+        let tree = Tree::new(BranchId::new("progress"), MemoryTrunk::new());
+        for i in 0..1000 {
+            tree.put(&format!("key-{:04}", i), Nut { value: vec![1] })
+                .unwrap();
+        }
+
+        let reports = std::cell::RefCell::new(Vec::new());
+        let exported = tree
+            .export_all_with_progress(std::io::sink(), &|p| reports.borrow_mut().push(p))
+            .unwrap();
+
+        let reports = reports.into_inner();
+        assert_eq!(exported, 1000);
+        assert_eq!(reports.len(), 10);
+        assert_eq!(
+            reports.last(),
+            Some(&Progress {
+                done: 1000,
+                total: Some(1000)
+            })
+        );
+    }
+
+    #[test]
+    fn shared_tree_survives_concurrent_mutation() {
+        fn assert_send_sync<X: Send + Sync>() {}
+        assert_send_sync::<Tree<Vec<u8>, MemoryTrunk>>();
+
+        let tree = Arc::new(Tree::new(BranchId::new("threads"), MemoryTrunk::new()));
+        let handles: Vec<_> = (0..8)
+            .map(|t| {
+                let tree = tree.clone();
+                std::thread::spawn(move || {
+                    for i in 0..200 {
+                        let key = format!("t{}-{}", t, i % 10);
+                        tree.put(
+                            &key,
+                            Nut {
+                                value: i.to_string().into_bytes(),
+                            },
+                        )
+                        .unwrap();
+                        assert!(tree.get(&key).unwrap().is_some());
+                        if i % 3 == 0 {
+                            tree.delete(&key).unwrap();
+                        }
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        for t in 0..8 {
+            for k in 0..10 {
+                let key = format!("t{}-{}", t, k);
+                let last = (0..200).filter(|i| i % 10 == k).max().unwrap();
+                let expected = (last % 3 != 0).then(|| last.to_string().into_bytes());
+                assert_eq!(tree.get(&key).unwrap().map(|n| n.value), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn racing_guarded_writes_have_one_winner_per_key() {
+        let tree = Arc::new(Tree::new(BranchId::new("race"), MemoryTrunk::new()));
+        let start = Arc::new(std::sync::Barrier::new(8));
+        let handles: Vec<_> = (0..8u8)
+            .map(|t| {
+                let (tree, start) = (tree.clone(), start.clone());
+                std::thread::spawn(move || {
+                    start.wait();
+                    let (mut created, mut bumped) = (Vec::new(), Vec::new());
+                    for k in 0..50 {
+                        let key = format!("shared-{k}");
+                        match tree
+                            .trunk()
+                            .put_if_absent(tree.branch(), &key, Nut { value: vec![t] })
+                        {
+                            Ok(()) => created.push(key.clone()),
+                            Err(AcornError::VersionConflict { .. }) => {}
+                            Err(e) => panic!("unexpected error: {e}"),
+                        }
+                        match tree.put_if_version(&key, Some(1), Nut { value: vec![t, 1] }) {
+                            Ok(()) => bumped.push((key, t)),
+                            Err(AcornError::VersionConflict { .. }) => {}
+                            Err(e) => panic!("unexpected error: {e}"),
+                        }
+                    }
+                    (created, bumped)
+                })
+            })
+            .collect();
+
+        let mut created: HashMap<String, usize> = HashMap::new();
+        let mut bumped: HashMap<String, Vec<u8>> = HashMap::new();
+        for handle in handles {
+            let (won_create, won_bump) = handle.join().unwrap();
+            for key in won_create {
+                *created.entry(key).or_default() += 1;
+            }
+            for (key, t) in won_bump {
+                bumped.entry(key).or_default().push(t);
+            }
+        }
+
+        for k in 0..50 {
+            let key = format!("shared-{k}");
+            assert_eq!(created.get(&key), Some(&1), "{key} created more than once");
+            let winners = &bumped[&key];
+            assert_eq!(winners.len(), 1, "{key} bumped by {winners:?}");
+            assert_eq!(tree.trunk().version(tree.branch(), &key), Some(2));
+            assert_eq!(tree.get(&key).unwrap().unwrap().value, vec![winners[0], 1]);
+        }
+    }
+
     #[cfg(feature = "contract-tests")]
     #[test]
     fn contract_round_trip_and_ttl() {
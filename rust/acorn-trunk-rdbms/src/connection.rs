@@ -0,0 +1,193 @@
+//! Structured connection settings that render a backend-specific connection URL.
+
+use std::fmt;
+use std::net::Ipv6Addr;
+
+use acorn_core::{AcornError, AcornResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DbBackend {
+    Postgres,
+    MySql,
+    SqlServer,
+}
+
+impl DbBackend {
+    fn scheme(self) -> &'static str {
+        match self {
+            DbBackend::Postgres => "postgres",
+            DbBackend::MySql => "mysql",
+            DbBackend::SqlServer => "mssql",
+        }
+    }
+
+    fn default_port(self) -> u16 {
+        match self {
+            DbBackend::Postgres => 5432,
+            DbBackend::MySql => 3306,
+            DbBackend::SqlServer => 1433,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SslMode {
+    Disable,
+    Prefer,
+    Require,
+}
+
+/// Builder for database connection settings. `host`, `user` and `database` are required; the port
+/// defaults to the backend's standard one.
+#[derive(Clone)]
+pub struct DbConnection {
+    backend: DbBackend,
+    host: Option<String>,
+    port: Option<u16>,
+    user: Option<String>,
+    password: Option<String>,
+    database: Option<String>,
+    ssl_mode: Option<SslMode>,
+}
+
+impl DbConnection {
+    pub fn new(backend: DbBackend) -> Self {
+        DbConnection {
+            backend,
+            host: None,
+            port: None,
+            user: None,
+            password: None,
+            database: None,
+            ssl_mode: None,
+        }
+    }
+
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    pub fn database(mut self, database: impl Into<String>) -> Self {
+        self.database = Some(database.into());
+        self
+    }
+
+    pub fn ssl_mode(mut self, ssl_mode: SslMode) -> Self {
+        self.ssl_mode = Some(ssl_mode);
+        self
+    }
+
+    /// Validate the settings and render the connection URL, percent-encoding user and password.
+    pub fn to_connection_string(&self) -> AcornResult<String> {
+        let host = url_host(required("host", &self.host)?)?;
+        let user = required("user", &self.user)?;
+        let database = required("database", &self.database)?;
+
+        let mut url = format!("{}://{}", self.backend.scheme(), percent_encode(user));
+        if let Some(password) = &self.password {
+            url.push(':');
+            url.push_str(&percent_encode(password));
+        }
+        let port = self.port.unwrap_or(self.backend.default_port());
+        url.push_str(&format!("@{host}:{port}/{}", percent_encode(database)));
+
+        let ssl = match (self.backend, self.ssl_mode) {
+            (_, None) => None,
+            (DbBackend::Postgres, Some(mode)) => Some(match mode {
+                SslMode::Disable => "sslmode=disable",
+                SslMode::Prefer => "sslmode=prefer",
+                SslMode::Require => "sslmode=require",
+            }),
+            (DbBackend::MySql, Some(mode)) => Some(match mode {
+                SslMode::Disable => "ssl-mode=DISABLED",
+                SslMode::Prefer => "ssl-mode=PREFERRED",
+                SslMode::Require => "ssl-mode=REQUIRED",
+            }),
+            (DbBackend::SqlServer, Some(mode)) => match mode {
+                SslMode::Disable => Some("encrypt=false"),
+                SslMode::Prefer => None,
+                SslMode::Require => Some("encrypt=true"),
+            },
+        };
+        if let Some(ssl) = ssl {
+            url.push('?');
+            url.push_str(ssl);
+        }
+        Ok(url)
+    }
+}
+
+/// Shows every setting except the password, which is masked.
+impl fmt::Debug for DbConnection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DbConnection")
+            .field("backend", &self.backend)
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("user", &self.user)
+            .field("password", &self.password.as_ref().map(|_| "***"))
+            .field("database", &self.database)
+            .field("ssl_mode", &self.ssl_mode)
+            .finish()
+    }
+}
+
+fn required<'a>(parameter: &'static str, value: &'a Option<String>) -> AcornResult<&'a str> {
+    match value.as_deref() {
+        Some(value) if !value.trim().is_empty() => Ok(value),
+        _ => Err(AcornError::Configuration {
+            parameter,
+            message: format!("{parameter} is required"),
+        }),
+    }
+}
+
+/// Bracket IPv6 literals and reject hosts that would change the URL's meaning (userinfo, port or path).
+fn url_host(host: &str) -> AcornResult<String> {
+    let inner = host
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .unwrap_or(host);
+    if inner.parse::<Ipv6Addr>().is_ok() {
+        return Ok(format!("[{inner}]"));
+    }
+    if host
+        .chars()
+        .any(|c| matches!(c, '@' | '/' | ':' | '?' | '#' | '[' | ']') || c.is_whitespace())
+    {
+        return Err(AcornError::Configuration {
+            parameter: "host",
+            message: format!("host {host:?} must be a hostname or IP address without userinfo, port or path"),
+        });
+    }
+    Ok(host.to_string())
+}
+
+/// Percent-encode everything outside the RFC 3986 unreserved set.
+fn percent_encode(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    for byte in raw.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    out
+}